@@ -0,0 +1,65 @@
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+// Clips characters play on their own: `idle` while standing still and `walk` while moving.
+pub const IDLE_CLIP: &str = "idle";
+pub const WALK_CLIP: &str = "walk";
+
+pub const DEFAULT_FRAMES_PER_SECOND: f32 = 10.0;
+
+fn default_frames_per_second() -> f32 {
+    DEFAULT_FRAMES_PER_SECOND
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+// Rectangle of a single animation frame in the sprite sheet, in texture pixels.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct FrameRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl FrameRect {
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+}
+
+// Sequence of frames in a sprite sheet, played back at a fixed rate.
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationClipData {
+    pub frames: Vec<FrameRect>,
+    #[serde(default = "default_frames_per_second")]
+    pub frames_per_second: f32,
+    // Clips that don't loop stop at their last frame.
+    #[serde(rename = "loop", default = "default_looping")]
+    pub looping: bool,
+}
+
+impl AnimationClipData {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.frames.is_empty() {
+            anyhow::bail!("animation has no frames");
+        }
+
+        if self.frames_per_second <= 0.0 {
+            anyhow::bail!("frames per second must be positive");
+        }
+
+        if self
+            .frames
+            .iter()
+            .any(|frame| frame.width <= 0.0 || frame.height <= 0.0)
+        {
+            anyhow::bail!("frame size must be positive");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use super::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// Character definition, e.g. `assets/character.json`. Characters don't have static images and
+// primitives like assets do, but a sprite per facing direction and a single collider, which is
+// also used for the character's shadow footprint.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterMetadata {
+    pub name: String,
+    // In world units per second.
+    pub movement_speed: f32,
+    pub directions: HashMap<AssetOrientation, CharacterImageData>,
+    // Characters can only have cuboid colliders for now.
+    pub collider: AssetColliderPrimitive,
+}
+
+#[derive(Deserialize)]
+pub struct CharacterImageData {
+    // Same as asset image URLs, see `resolve_image_path`.
+    pub url: String,
+    pub transform: CharacterTransform,
+    // Animation clips by name, with frames taken from the image as a sprite sheet. Without any, the
+    // whole image is drawn as a still sprite.
+    #[serde(default)]
+    pub clips: HashMap<String, AnimationClipData>,
+}
+
+// Character sprites are only scaled along with the scene, so unlike asset images they have no
+// scale of their own.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterTransform {
+    // Offset of the sprite relative to the character position.
+    pub position: Point,
+    pub front_point: Point,
+}
+
+impl CharacterMetadata {
+    // Direction the character faces when spawned: towards the viewer if there's a sprite for it.
+    pub fn default_direction(&self) -> (AssetOrientation, &CharacterImageData) {
+        if let Some(image) = self.directions.get(&AssetOrientation::South) {
+            return (AssetOrientation::South, image);
+        }
+
+        // Loading makes sure there's at least one direction.
+        AssetOrientation::ALL
+            .iter()
+            .find_map(|x| Some((*x, self.directions.get(x)?)))
+            .unwrap()
+    }
+}
+
+pub struct CharacterAsset {
+    // Path the character was loaded from, so that scenes can refer back to it when saved.
+    pub path: String,
+    pub metadata: CharacterMetadata,
+}
+
+// Paths of all the images of a character, as they should be read from an asset source.
+pub fn character_image_paths(character: &CharacterAsset) -> Vec<String> {
+    character
+        .metadata
+        .directions
+        .values()
+        .map(|image| resolve_image_path(&image.url, &character.path))
+        .collect()
+}
+
+// Loads character metadata only. Character images are loaded along with the scene textures.
+pub fn load_character(source: &dyn AssetSource, path: &str) -> anyhow::Result<CharacterAsset> {
+    println!("loading character metadata: {path}");
+
+    let data = source.read(path)?;
+    let metadata = parse_character_metadata(&data).map_err(|err| anyhow::anyhow!("{path}: {err}"))?;
+
+    Ok(CharacterAsset {
+        path: path.to_owned(),
+        metadata,
+    })
+}
+
+pub fn load_character_bundle(source: &dyn AssetSource, paths: &[&str]) -> anyhow::Result<Vec<CharacterAsset>> {
+    paths.iter().map(|path| load_character(source, path)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_shipped_character() {
+        let character = load_character(&DirectorySource::new(""), "assets/character.json").unwrap();
+        let (orientation, _) = character.metadata.default_direction();
+
+        assert_eq!(orientation, AssetOrientation::South);
+        assert_eq!(
+            character_image_paths(&character),
+            vec![String::from("assets/character.png")]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use super::*;
+use crate::render::create_texture_resource_from_rgba8;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+struct DecodedImage {
+    width: u16,
+    height: u16,
+    data: Vec<u8>,
+}
+
+fn decode_image(source: &dyn AssetSource, path: &str) -> anyhow::Result<DecodedImage> {
+    // This assumes that all images are in `png` format, same as `TextureWrapper`.
+    let image = image::load_from_memory_with_format(&source.read(path)?, image::ImageFormat::Png)?.to_rgba8();
+
+    // Textures are limited to 16 bit sizes.
+    let (width, height) = match (u16::try_from(image.width()), u16::try_from(image.height())) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => anyhow::bail!("image is too large: {}x{}", image.width(), image.height()),
+    };
+
+    Ok(DecodedImage {
+        width,
+        height,
+        data: image.into_raw(),
+    })
+}
+
+// Reads and decodes images on worker threads, since PNG decoding is by far the slowest part of
+// loading. Textures can only be created on the main thread though, so that happens in `poll` as the
+// decoded images come in.
+pub struct ImageLoader {
+    receiver: Receiver<(String, anyhow::Result<DecodedImage>)>,
+    textures: HashMap<String, TextureResource>,
+    total: usize,
+}
+
+impl ImageLoader {
+    pub fn start(source: Arc<dyn AssetSource>, mut paths: Vec<String>) -> Self {
+        paths.sort();
+        paths.dedup();
+
+        let total = paths.len();
+        let worker_count = thread::available_parallelism().map_or(1, |x| x.get()).min(total);
+        let queue = Arc::new(Mutex::new(paths));
+        let (sender, receiver) = mpsc::channel();
+
+        println!("loading {total} images on {worker_count} threads");
+
+        for _ in 0..worker_count {
+            let source = source.clone();
+            let queue = queue.clone();
+            let sender = sender.clone();
+
+            thread::spawn(move || loop {
+                let path = match queue.lock().unwrap().pop() {
+                    Some(path) => path,
+                    None => break,
+                };
+
+                let result = decode_image(source.as_ref(), &path);
+
+                // The receiving end is gone if loading has been abandoned.
+                if sender.send((path, result)).is_err() {
+                    break;
+                }
+            });
+        }
+
+        Self {
+            receiver,
+            textures: HashMap::new(),
+            total,
+        }
+    }
+
+    // Creates textures for all the images decoded so far. Returns `true` once all the images are
+    // loaded.
+    pub fn poll(&mut self) -> anyhow::Result<bool> {
+        while !self.is_done() {
+            let (path, result) = match self.receiver.try_recv() {
+                Ok(received) => received,
+                Err(TryRecvError::Empty) => break,
+                // Workers only stop early if they crashed, so the remaining images will never come.
+                Err(TryRecvError::Disconnected) => anyhow::bail!(
+                    "image loading stopped with {} of {} images loaded",
+                    self.textures.len(),
+                    self.total
+                ),
+            };
+
+            let image = match result {
+                Ok(image) => image,
+                Err(err) => anyhow::bail!("failed to load image {path}: {err}"),
+            };
+
+            let texture = create_texture_resource_from_rgba8(image.width, image.height, &image.data);
+            self.textures.insert(path, texture);
+        }
+
+        Ok(self.is_done())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.textures.len() == self.total
+    }
+
+    // Number of loaded images and the total number of images.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.textures.len(), self.total)
+    }
+
+    pub fn into_textures(self) -> HashMap<String, TextureResource> {
+        self.textures
+    }
+}
@@ -1,53 +1,47 @@
 use super::*;
 use crate::render::create_texture_resource;
-use sha2::{Digest, Sha256};
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufReader, BufWriter, Read, Write},
-    path::Path,
-};
-
-const CACHE_DIR: &str = ".cache";
-
-fn load_data_from_url(url: &str) -> anyhow::Result<bytes::Bytes> {
-    // This wasn't necessary, but resource loading took awfully long time on each program start,
-    // so I thought that this may actually save some development time.
-    let hash = Sha256::new().chain_update(url).finalize();
-    let cache_file = format!("{CACHE_DIR}/{:x}.png", hash);
-    let cache_file = Path::new(&cache_file);
-
-    if cache_file.exists() {
-        println!("loading cached data: {cache_file:?}");
-
-        // Correction: it wasn't slow because of download, but because of PNG decoding... oh well =\
-        let mut stream = BufReader::new(File::open(cache_file)?);
-        let mut buffer = vec![];
-        stream.read_to_end(&mut buffer)?;
-
-        Ok(bytes::Bytes::from(buffer))
+use macroquad::prelude::{vec2, Vec2};
+use std::{collections::HashMap, io::Cursor};
+
+// Image URLs in asset metadata can either be remote (`http://` or `https://`), local files
+// (`file://`) or paths relative to the metadata file, which is what the `package-assets` tool
+// writes. Either way, it's up to the source to actually read the data.
+pub fn resolve_image_path(url: &str, metadata_path: &str) -> String {
+    if url.contains("://") {
+        url.to_owned()
     } else {
-        println!("downloading data: {url}");
+        resolve_relative_path(metadata_path, url)
+    }
+}
 
-        let result = reqwest::blocking::get(url)?.bytes()?;
-        let mut stream = BufWriter::new(File::create(cache_file)?);
-        stream.write(&result)?;
+pub fn load_image_data(source: &dyn AssetSource, url: &str, metadata_path: &str) -> anyhow::Result<bytes::Bytes> {
+    source.read(&resolve_image_path(url, metadata_path))
+}
 
-        Ok(result)
-    }
+// Paths of all the images of an asset, as they should be read from an asset source.
+pub fn asset_image_paths(asset: &AssetMetadata, metadata_path: &str) -> Vec<String> {
+    asset
+        .orientations
+        .iter()
+        .flat_map(|(_, x)| x.images.iter())
+        .map(|(_, image)| resolve_image_path(&image.url, metadata_path))
+        .collect()
 }
 
-fn load_asset_metadata_from_file(path: &str) -> anyhow::Result<AssetMetadata> {
+pub fn load_asset_metadata(source: &dyn AssetSource, path: &str) -> anyhow::Result<AssetMetadata> {
     println!("loading asset metadata: {path}");
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let input = serde_json::from_reader(reader)?;
+    let data = source.read(path)?;
 
-    Ok(input)
+    parse_asset_metadata(&data).map_err(|err| anyhow::anyhow!("{path}: {err}"))
 }
 
-fn load_asset_resources(asset: &AssetMetadata) -> anyhow::Result<AssetResourceList> {
+// Creates the resources of an asset, with textures provided by `load_texture` given image paths.
+pub fn load_asset_resources(
+    asset: &AssetMetadata,
+    metadata_path: &str,
+    mut load_texture: impl FnMut(&str) -> anyhow::Result<TextureResource>,
+) -> anyhow::Result<AssetResourceList> {
     let mut result = HashMap::new();
 
     let texture_list = asset
@@ -59,33 +53,60 @@ fn load_asset_resources(asset: &AssetMetadata) -> anyhow::Result<AssetResourceLi
         .collect::<Vec<_>>();
 
     for (image_id, image_url) in texture_list {
-        let texture = create_texture_resource(load_data_from_url(image_url)?);
+        let texture = load_texture(&resolve_image_path(image_url, metadata_path))?;
         let insert_result = result.insert(image_id.clone(), texture);
 
-        // Make sure resources are unique.
-        assert!(insert_result.is_none());
+        // Make sure resources are unique. Validation should've caught this already.
+        if insert_result.is_some() {
+            anyhow::bail!("duplicate image ID in {metadata_path}: {image_id}");
+        }
     }
 
     Ok(result)
 }
 
-pub fn load_asset(path: &str) -> anyhow::Result<Asset> {
+pub fn load_asset(source: &dyn AssetSource, path: &str) -> anyhow::Result<Asset> {
     // Entire asset loading call is blocking, because of the issues with `reqwest` async loader
-    // requiring `tokio` reactor running for async requests. Didn't have time to fix :(
-    let metadata = load_asset_metadata_from_file(&path)?;
-    let resources = load_asset_resources(&metadata)?;
+    // requiring `tokio` reactor running for async requests. See `SceneLoader` for loading scenes
+    // without blocking.
+    let metadata = load_asset_metadata(source, path)?;
+    let resources = load_asset_resources(&metadata, path, |image_path| load_texture(source, image_path))?;
+
+    Ok(Asset {
+        path: path.to_owned(),
+        metadata,
+        resources: Some(resources),
+    })
+}
 
-    Ok(Asset { metadata, resources })
+// Loads asset metadata only, without any of the images. Useful for running scenes without a window,
+// e.g. in tests.
+pub fn load_asset_headless(source: &dyn AssetSource, path: &str) -> anyhow::Result<Asset> {
+    let metadata = load_asset_metadata(source, path)?;
+
+    Ok(Asset {
+        path: path.to_owned(),
+        metadata,
+        resources: None,
+    })
+}
+
+pub fn load_asset_bundle(source: &dyn AssetSource, assets: &[&str]) -> anyhow::Result<Vec<Asset>> {
+    load_asset_bundle_with(assets, |path| load_asset(source, path))
 }
 
-pub fn load_asset_bundle(assets: &[&str]) -> anyhow::Result<Vec<Asset>> {
+pub fn load_asset_bundle_headless(source: &dyn AssetSource, assets: &[&str]) -> anyhow::Result<Vec<Asset>> {
+    load_asset_bundle_with(assets, |path| load_asset_headless(source, path))
+}
+
+fn load_asset_bundle_with(assets: &[&str], load: impl Fn(&str) -> anyhow::Result<Asset>) -> anyhow::Result<Vec<Asset>> {
     println!("loading asset bundle: {} assets", assets.len());
 
-    let current_dir: String = String::from(std::env::current_dir()?.as_path().to_str().unwrap());
     let mut result = vec![];
 
+    // Asset names are kept as logical paths, since scene files refer to them by these names.
     for asset_name in assets {
-        result.push(load_asset(&format!("{current_dir}/{asset_name}"))?);
+        result.push(load(asset_name)?);
     }
 
     println!("bundle loaded successfully");
@@ -93,12 +114,18 @@ pub fn load_asset_bundle(assets: &[&str]) -> anyhow::Result<Vec<Asset>> {
     Ok(result)
 }
 
-pub fn load_texture_from_file(path: &str) -> anyhow::Result<TextureResource> {
-    println!("loading texture from file: {path:?}");
+pub fn load_texture(source: &dyn AssetSource, path: &str) -> anyhow::Result<TextureResource> {
+    println!("loading texture: {path:?}");
+
+    Ok(create_texture_resource(source.read(path)?))
+}
 
-    let mut stream = BufReader::new(File::open(path)?);
-    let mut buffer = vec![];
-    stream.read_to_end(&mut buffer)?;
+// Size of an image in pixels, read from its header without decoding the whole image. Like
+// textures, images are assumed to be in `png` format.
+pub fn load_image_size(source: &dyn AssetSource, path: &str) -> anyhow::Result<Vec2> {
+    let data = source.read(path)?;
+    let (width, height) =
+        image::io::Reader::with_format(Cursor::new(data), image::ImageFormat::Png).into_dimensions()?;
 
-    Ok(create_texture_resource(bytes::Bytes::from(buffer)))
+    Ok(vec2(width as f32, height as f32))
 }
@@ -1,9 +1,10 @@
+use super::AnimationClipData;
 use macroquad::prelude::*;
-use serde::Deserialize;
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 
 // Use `Point` instead of `glam::Vec2`, since glam's serde implementation serializes into a tuple.
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
@@ -16,13 +17,21 @@ impl Point {
     }
 }
 
+impl From<Vec2> for Point {
+    fn from(v: Vec2) -> Self {
+        Self { x: v.x, y: v.y }
+    }
+}
+
 impl From<Point> for Vec2 {
     fn from(pt: Point) -> Self {
         pt.vec2()
     }
 }
 
-#[derive(Deserialize, Hash, PartialEq, Eq, Debug, Clone, Copy)]
+// Facing direction in isometric space. Assets only come in the four cardinal orientations, while
+// characters can also face diagonally.
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, Debug, Clone, Copy)]
 pub enum AssetOrientation {
     #[serde(rename = "N")]
     North,
@@ -32,12 +41,90 @@ pub enum AssetOrientation {
     East,
     #[serde(rename = "W")]
     West,
+    #[serde(rename = "NE")]
+    NorthEast,
+    #[serde(rename = "SE")]
+    SouthEast,
+    #[serde(rename = "SW")]
+    SouthWest,
+    #[serde(rename = "NW")]
+    NorthWest,
+}
+
+impl AssetOrientation {
+    // All orientations, in clockwise order, 45 degrees apart.
+    pub const ALL: [AssetOrientation; 8] = [
+        AssetOrientation::North,
+        AssetOrientation::NorthEast,
+        AssetOrientation::East,
+        AssetOrientation::SouthEast,
+        AssetOrientation::South,
+        AssetOrientation::SouthWest,
+        AssetOrientation::West,
+        AssetOrientation::NorthWest,
+    ];
+
+    #[inline]
+    fn index(self) -> usize {
+        AssetOrientation::ALL.iter().position(|x| *x == self).unwrap()
+    }
+
+    // Orientation closest to the given isometric space direction, or `None` for zero direction.
+    // `N` is `-y` and `E` is `+x`, see the readme.
+    pub fn from_direction(direction: Vec2) -> Option<Self> {
+        if direction == Vec2::ZERO {
+            return None;
+        }
+
+        // Clockwise angle from `N`.
+        let angle = direction.x.atan2(-direction.y);
+        let steps = (angle / std::f32::consts::FRAC_PI_4).round() as i32;
+
+        Some(AssetOrientation::ALL[steps.rem_euclid(8) as usize])
+    }
+
+    // Only characters can face diagonally.
+    pub fn is_diagonal(self) -> bool {
+        self.index() % 2 == 1
+    }
+
+    // Number of 45 degree steps between two orientations, either way around.
+    pub fn steps_to(self, other: Self) -> usize {
+        let steps = (other.index() + 8 - self.index()) % 8;
+        steps.min(8 - steps)
+    }
+
+    // Orientation that looks the same when mirrored horizontally on screen, e.g. `E` (down and
+    // right) for `S` (down and left).
+    pub fn mirrored(self) -> Self {
+        match self {
+            AssetOrientation::North => AssetOrientation::West,
+            AssetOrientation::West => AssetOrientation::North,
+            AssetOrientation::East => AssetOrientation::South,
+            AssetOrientation::South => AssetOrientation::East,
+            AssetOrientation::NorthEast => AssetOrientation::SouthWest,
+            AssetOrientation::SouthWest => AssetOrientation::NorthEast,
+            AssetOrientation::SouthEast | AssetOrientation::NorthWest => self,
+        }
+    }
+}
+
+// Opacity of shadows for assets which don't specify their own.
+pub const DEFAULT_SHADOW_OPACITY: f32 = 0.15;
+
+fn default_shadow_opacity() -> f32 {
+    DEFAULT_SHADOW_OPACITY
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AssetMetadata {
     pub name: String,
     pub orientations: HashMap<AssetOrientation, AssetOrientationData>,
+    // Opacity of all the shadows of the asset. Overlapping shadows don't get any darker than the
+    // darkest one of them.
+    #[serde(default = "default_shadow_opacity")]
+    pub shadow_opacity: f32,
 }
 
 impl AssetMetadata {
@@ -50,6 +137,17 @@ impl AssetMetadata {
             anyhow::bail!("asset \"{}\" does not support orientation {orientation:?}", self.name)
         }
     }
+
+    // Next supported orientation, clockwise from the given one. Returns the given orientation if
+    // it's the only one supported.
+    pub fn next_orientation(&self, orientation: AssetOrientation) -> AssetOrientation {
+        let index = orientation.index();
+
+        (1..=AssetOrientation::ALL.len())
+            .map(|offset| AssetOrientation::ALL[(index + offset) % AssetOrientation::ALL.len()])
+            .find(|x| self.orientations.contains_key(x))
+            .unwrap_or(orientation)
+    }
 }
 
 #[derive(Deserialize)]
@@ -64,23 +162,24 @@ pub struct AssetImageData {
     pub url: String,
     pub transform: AssetTransform,
     pub primitives: HashMap<String, AssetPrimitive>,
+    // Optional looping animation, with frames taken from the image as a sprite sheet.
+    #[serde(default)]
+    pub animation: Option<AnimationClipData>,
 }
 
 impl AssetImageData {
-    pub fn get_primitives(&self, ty: AssetPrimitiveType) -> Option<Vec<AssetPrimitive>> {
-        let mut result = vec![];
-
-        for (_, x) in &self.primitives {
-            if x.ty == ty {
-                result.push(*x);
-            }
-        }
+    pub fn shadows(&self) -> impl Iterator<Item = &AssetShadowPrimitive> {
+        self.primitives.values().filter_map(|x| match x {
+            AssetPrimitive::Shadow(shadow) => Some(shadow),
+            _ => None,
+        })
+    }
 
-        if result.len() > 0 {
-            Some(result)
-        } else {
-            None
-        }
+    pub fn colliders(&self) -> impl Iterator<Item = &AssetColliderPrimitive> {
+        self.primitives.values().filter_map(|x| match x {
+            AssetPrimitive::Collider(collider) => Some(collider),
+            _ => None,
+        })
     }
 }
 
@@ -105,29 +204,259 @@ pub enum AssetPrimitiveType {
     Shadow,
 }
 
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Clone)]
+pub enum AssetPrimitive {
+    Shadow(AssetShadowPrimitive),
+    Collider(AssetColliderPrimitive),
+}
+
+#[derive(Clone)]
+pub struct AssetShadowPrimitive {
+    pub shape: AssetPrimitiveShape,
+    pub shadow_type: AssetShapeType,
+    // Width of the blurred edge of the shadow, in asset units. The edge fades out from the full
+    // opacity inside the shape to nothing outside of it, centered on the shape outline. Zero means
+    // a hard edge.
+    pub softness: f32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct AssetColliderPrimitive {
+    pub shape: AssetPrimitiveShape,
+    pub height: f32,
+}
+
+// Primitives are stored flat in the metadata, with the `type` field deciding which of the other
+// fields are required. Deserialize into this first, and then check the combination of fields.
+#[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct AssetPrimitive {
+struct RawAssetPrimitive {
     #[serde(rename = "type")]
-    pub ty: AssetPrimitiveType,
-    pub shape: AssetPrimitiveShape,
+    ty: AssetPrimitiveType,
+    shape: AssetPrimitiveShape,
+    shadow_type: Option<AssetShapeType>,
+    softness: Option<f32>,
+    height: Option<f32>,
+}
+
+impl<'de> Deserialize<'de> for AssetPrimitive {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawAssetPrimitive::deserialize(deserializer)?;
 
-    // Ideally, these should not be optional and instead shadows and colliders should deserialize
-    // into different structs, but that may require a custom deserializer and we don't care about
-    // that now.
-    pub shadow_type: Option<AssetShapeType>,
-    pub height: Option<f32>,
+        match raw {
+            RawAssetPrimitive {
+                ty: AssetPrimitiveType::Shadow,
+                shape,
+                shadow_type: Some(shadow_type),
+                softness,
+                height: None,
+            } => Ok(AssetPrimitive::Shadow(AssetShadowPrimitive {
+                shape,
+                shadow_type,
+                softness: softness.unwrap_or(0.0),
+            })),
+            RawAssetPrimitive {
+                ty: AssetPrimitiveType::Shadow,
+                shadow_type: None,
+                ..
+            } => Err(D::Error::missing_field("shadowType")),
+            RawAssetPrimitive {
+                ty: AssetPrimitiveType::Shadow,
+                ..
+            } => Err(D::Error::custom("shadows can't have a height")),
+            RawAssetPrimitive {
+                ty: AssetPrimitiveType::Collider,
+                shape,
+                shadow_type: None,
+                softness: None,
+                height: Some(height),
+            } => Ok(AssetPrimitive::Collider(AssetColliderPrimitive { shape, height })),
+            RawAssetPrimitive {
+                ty: AssetPrimitiveType::Collider,
+                height: None,
+                ..
+            } => Err(D::Error::missing_field("height")),
+            RawAssetPrimitive {
+                ty: AssetPrimitiveType::Collider,
+                shadow_type: Some(_),
+                ..
+            } => Err(D::Error::custom("colliders can't have a shadow type")),
+            RawAssetPrimitive {
+                ty: AssetPrimitiveType::Collider,
+                ..
+            } => Err(D::Error::custom("colliders can't have softness")),
+        }
+    }
 }
 
-#[derive(Deserialize, Clone, Copy)]
+// Number of segments ellipses are approximated with. Should be a multiple of 4, so that the
+// approximation touches the shape bounds on every side.
+pub const ELLIPSE_SEGMENT_COUNT: usize = 32;
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
 #[serde(rename_all = "camelCase")]
 pub enum AssetShapeType {
     Rect,
+    Circle,
+    Ellipse,
+    Polygon,
 }
 
-#[derive(Deserialize, Clone, Copy)]
+// Every shape is defined by its bounding rect (`position` and `scale`). Circles and ellipses are
+// inscribed into the rect, and polygon points are normalized to it, i.e. `{"x": 0, "y": 0}` is the
+// top left corner of the rect and `{"x": 1, "y": 1}` is the bottom right one. Polygons have to be
+// convex, concave shapes should be split into several primitives.
+#[derive(Deserialize, Clone)]
 pub struct AssetPrimitiveShape {
     pub position: Point,
     pub scale: Point,
     pub shape: AssetShapeType,
+    #[serde(default)]
+    pub points: Vec<Point>,
+}
+
+impl AssetPrimitiveShape {
+    // Outline of the shape in asset space, as a convex polygon.
+    pub fn outline(&self) -> Vec<Vec2> {
+        let position = self.position.vec2();
+        let scale = self.scale.vec2();
+
+        match self.shape {
+            AssetShapeType::Rect => vec![
+                position,
+                position + vec2(scale.x, 0.0),
+                position + scale,
+                position + vec2(0.0, scale.y),
+            ],
+            AssetShapeType::Circle | AssetShapeType::Ellipse => {
+                let center = position + scale * 0.5;
+
+                (0..ELLIPSE_SEGMENT_COUNT)
+                    .map(|i| {
+                        let angle = i as f32 / ELLIPSE_SEGMENT_COUNT as f32 * std::f32::consts::TAU;
+                        center + vec2(angle.cos(), angle.sin()) * scale * 0.5
+                    })
+                    .collect()
+            }
+            AssetShapeType::Polygon => self.points.iter().map(|x| position + x.vec2() * scale).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deserializes a primitive with a unit rect shape and the given other fields.
+    fn primitive(fields: &str) -> Result<AssetPrimitive, String> {
+        let shape = r#""shape": { "position": { "x": 0, "y": 0 }, "scale": { "x": 1, "y": 1 }, "shape": "rect" }"#;
+        serde_json::from_str(&format!("{{ {shape}, {fields} }}")).map_err(|err| err.to_string())
+    }
+
+    fn primitive_error(fields: &str) -> String {
+        match primitive(fields) {
+            Ok(_) => panic!("primitive with {fields} should be rejected"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn deserializes_shadows() {
+        match primitive(r#""type": "shadow", "shadowType": "ellipse", "softness": 12"#) {
+            Ok(AssetPrimitive::Shadow(shadow)) => {
+                assert_eq!(shadow.shadow_type, AssetShapeType::Ellipse);
+                assert_eq!(shadow.softness, 12.0);
+                assert_eq!(shadow.shape.shape, AssetShapeType::Rect);
+            }
+            _ => panic!("expected a shadow"),
+        }
+
+        match primitive(r#""type": "shadow", "shadowType": "rect""#) {
+            Ok(AssetPrimitive::Shadow(shadow)) => assert_eq!(shadow.softness, 0.0),
+            _ => panic!("expected a shadow"),
+        }
+    }
+
+    #[test]
+    fn deserializes_colliders() {
+        match primitive(r#""type": "collider", "height": 40"#) {
+            Ok(AssetPrimitive::Collider(collider)) => {
+                assert_eq!(collider.height, 40.0);
+                assert_eq!(collider.shape.shape, AssetShapeType::Rect);
+            }
+            _ => panic!("expected a collider"),
+        }
+    }
+
+    fn shape(fields: &str) -> Result<AssetPrimitiveShape, String> {
+        let bounds = r#""position": { "x": 1, "y": 2 }, "scale": { "x": 3, "y": 4 }"#;
+        serde_json::from_str(&format!("{{ {bounds}, {fields} }}")).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn deserializes_shapes() {
+        for (name, ty) in [
+            ("rect", AssetShapeType::Rect),
+            ("circle", AssetShapeType::Circle),
+            ("ellipse", AssetShapeType::Ellipse),
+        ] {
+            let shape = shape(&format!(r#""shape": "{name}""#)).unwrap();
+            assert_eq!(shape.shape, ty);
+            assert_eq!(shape.position, Point { x: 1.0, y: 2.0 });
+            assert_eq!(shape.scale, Point { x: 3.0, y: 4.0 });
+            assert!(shape.points.is_empty());
+        }
+
+        let polygon =
+            shape(r#""shape": "polygon", "points": [{ "x": 0, "y": 0 }, { "x": 1, "y": 0 }, { "x": 0, "y": 1 }]"#)
+                .unwrap();
+        assert_eq!(polygon.shape, AssetShapeType::Polygon);
+        assert_eq!(polygon.points.len(), 3);
+
+        assert!(shape(r#""shape": "hexagon""#)
+            .unwrap_err()
+            .contains("unknown variant `hexagon`"));
+        assert!(shape(r#""shape": "polygon", "points": [1, 2]"#).is_err());
+        assert!(serde_json::from_str::<AssetPrimitiveShape>(r#"{ "shape": "rect" }"#).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_primitives() {
+        assert!(primitive_error(r#""type": "shadow""#).contains("missing field `shadowType`"));
+        assert!(
+            primitive_error(r#""type": "shadow", "shadowType": "rect", "height": 40"#)
+                .contains("shadows can't have a height")
+        );
+
+        assert!(primitive_error(r#""type": "collider""#).contains("missing field `height`"));
+        assert!(
+            primitive_error(r#""type": "collider", "height": 40, "shadowType": "rect""#)
+                .contains("colliders can't have a shadow type")
+        );
+        assert!(primitive_error(r#""type": "collider", "height": 40, "softness": 12"#)
+            .contains("colliders can't have softness"));
+
+        assert!(primitive_error(r#""type": "light""#).contains("unknown variant `light`"));
+    }
+
+    #[test]
+    fn orientation_from_direction() {
+        assert_eq!(
+            AssetOrientation::from_direction(vec2(0.0, -1.0)),
+            Some(AssetOrientation::North)
+        );
+        assert_eq!(
+            AssetOrientation::from_direction(vec2(1.0, 0.0)),
+            Some(AssetOrientation::East)
+        );
+        assert_eq!(
+            AssetOrientation::from_direction(vec2(1.0, 1.0)),
+            Some(AssetOrientation::SouthEast)
+        );
+        assert_eq!(
+            AssetOrientation::from_direction(vec2(-1.0, 0.1)),
+            Some(AssetOrientation::West)
+        );
+        assert_eq!(AssetOrientation::from_direction(vec2(0.0, 0.0)), None);
+    }
 }
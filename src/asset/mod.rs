@@ -1,23 +1,45 @@
+mod animation;
+mod character;
+mod image_loader;
 mod loader;
 mod metadata;
+mod package;
+mod source;
+mod validation;
 
 use crate::render::TextureResource;
+pub use animation::*;
+pub use character::*;
+pub use image_loader::*;
 pub use loader::*;
 pub use metadata::*;
+pub use package::*;
+pub use source::*;
 use std::collections::HashMap;
+pub use validation::*;
 
 pub type AssetResourceList = HashMap<String, TextureResource>;
 
 pub struct Asset {
+    // Path the asset was loaded from, so that scenes can refer back to it when saved.
+    pub path: String,
     pub metadata: AssetMetadata,
-    pub resources: AssetResourceList,
+    // Image resources, or `None` if the asset was loaded headless (i.e. without a window to create
+    // textures in).
+    pub resources: Option<AssetResourceList>,
 }
 
 impl Asset {
-    pub fn get_texture_resource(&self, name: &String) -> anyhow::Result<TextureResource> {
+    pub fn get_texture_resource(&self, name: &String) -> anyhow::Result<Option<TextureResource>> {
+        // Headless assets don't have any textures, and that's fine.
+        let resources = match &self.resources {
+            Some(resources) => resources,
+            None => return Ok(None),
+        };
+
         // This should probably return `Option` instead of `Result`.
-        if let Some(tex) = self.resources.get(name) {
-            Ok(tex.clone())
+        if let Some(tex) = resources.get(name) {
+            Ok(Some(tex.clone()))
         } else {
             anyhow::bail!("resource not available: {name}")
         }
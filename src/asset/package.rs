@@ -0,0 +1,150 @@
+use super::*;
+use serde_json::Value;
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+// Packages an asset for offline use: resolves all of its images once, writes them into
+// `<output_dir>/<asset name>/` and writes a copy of the metadata into `<output_dir>`, with image URLs
+// rewritten to point to the local files. Returns the path of the packaged metadata file.
+pub fn package_asset(source: &dyn AssetSource, path: &str, output_dir: &Path) -> anyhow::Result<PathBuf> {
+    println!("packaging asset: {path}");
+
+    let asset_name = match Path::new(path).file_stem().and_then(|x| x.to_str()) {
+        Some(asset_name) => asset_name,
+        None => anyhow::bail!("invalid asset path: {path}"),
+    };
+
+    // Work on raw JSON rather than `AssetMetadata`, so that the packaged metadata is an exact copy
+    // of the original apart from the image URLs. Still, make sure it's valid before going further.
+    let data = source.read(path)?;
+    parse_asset_metadata(&data).map_err(|err| anyhow::anyhow!("{path}: {err}"))?;
+    let mut metadata: Value = serde_json::from_slice(&data)?;
+
+    let image_dir = output_dir.join(asset_name);
+    fs::create_dir_all(&image_dir)?;
+
+    let orientations = metadata["orientations"]
+        .as_object_mut()
+        .into_iter()
+        .flat_map(|x| x.values_mut());
+    let images = orientations.flat_map(|x| x["images"].as_object_mut().into_iter().flat_map(|x| x.iter_mut()));
+
+    for (image_id, image) in images {
+        let url = match image["url"].as_str() {
+            Some(url) => url,
+            None => anyhow::bail!("image \"{image_id}\" of asset {path} has no url"),
+        };
+
+        let data = load_image_data(source, url, path)?;
+        let file_name = format!("{image_id}.png");
+        fs::write(image_dir.join(&file_name), &data)?;
+
+        // URLs are relative to the metadata file, see `resolve_image_path`.
+        image["url"] = Value::String(format!("{asset_name}/{file_name}"));
+    }
+
+    let output_path = output_dir.join(format!("{asset_name}.json"));
+    serde_json::to_writer_pretty(BufWriter::new(File::create(&output_path)?), &metadata)?;
+
+    Ok(output_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA: &str = r#"{
+        "name": "Crate",
+        "orientations": {
+            "N": {
+                "images": {
+                    "front": {
+                        "type": "image",
+                        "url": "URL",
+                        "transform": {
+                            "position": { "x": 0, "y": 0 },
+                            "scale": { "x": 1, "y": 1 },
+                            "frontPoint": { "x": 0, "y": 0 }
+                        },
+                        "primitives": {}
+                    }
+                }
+            }
+        }
+    }"#;
+
+    // Fresh directory for a test, unique per process so that concurrent test runs don't interfere.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sophya_package_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Packages the asset with the given image URL from `<dir>/source/crate.json`, and checks that
+    // the image ends up next to the packaged metadata, which refers to it by a relative URL.
+    fn assert_packages_image(dir: &Path, url: &str, image_data: &[u8]) {
+        let source_dir = dir.join("source");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(source_dir.join("crate.json"), METADATA.replace("URL", url)).unwrap();
+
+        let output_dir = dir.join("output");
+        let source = DirectorySource::new(&source_dir);
+        let output_path = package_asset(&source, "crate.json", &output_dir).unwrap();
+
+        assert_eq!(output_path, output_dir.join("crate.json"));
+        assert_eq!(fs::read(output_dir.join("crate/front.png")).unwrap(), image_data);
+
+        let metadata = parse_asset_metadata(&fs::read(&output_path).unwrap()).unwrap();
+        let image = &metadata.get_orientation(AssetOrientation::North).unwrap().images["front"];
+        assert_eq!(image.url, "crate/front.png");
+
+        // The packaged asset loads from the output directory alone.
+        let packaged_source = DirectorySource::new(&output_dir);
+        let data = load_image_data(&packaged_source, &image.url, "crate.json").unwrap();
+        assert_eq!(&data[..], image_data);
+    }
+
+    #[test]
+    fn packages_relative_image() {
+        let dir = test_dir("relative");
+        let image_data = [1, 2, 3];
+
+        fs::create_dir_all(dir.join("source/images")).unwrap();
+        fs::write(dir.join("source/images/crate_front.png"), image_data).unwrap();
+
+        assert_packages_image(&dir, "images/crate_front.png", &image_data);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn packages_file_image() {
+        let dir = test_dir("file");
+        let image_data = [4, 5, 6];
+
+        // Outside of the source directory, so that it can only be found through the `file://` URL.
+        let image_path = dir.join("crate_front.png");
+        fs::write(&image_path, image_data).unwrap();
+
+        assert_packages_image(&dir, &format!("file://{}", image_path.to_str().unwrap()), &image_data);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_image_fails() {
+        let dir = test_dir("missing");
+
+        fs::create_dir_all(dir.join("source")).unwrap();
+        fs::write(dir.join("source/crate.json"), METADATA.replace("URL", "missing.png")).unwrap();
+
+        let source = DirectorySource::new(dir.join("source"));
+        assert!(package_asset(&source, "crate.json", &dir.join("output")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,306 @@
+use bytes::{Buf, BufMut, Bytes};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+pub const CACHE_DIR: &str = ".cache";
+pub const DATA_PACK_FILE: &str = "data.pack";
+
+const PACK_MAGIC: &[u8; 4] = b"SPAK";
+const PACK_VERSION: u32 = 1;
+
+// Source of asset data. Everything the engine loads - scene files, asset metadata and images - is
+// read through a source by its logical path, e.g. `assets/chair.json` or an image URL.
+pub trait AssetSource: Send + Sync {
+    fn read(&self, path: &str) -> anyhow::Result<Bytes>;
+}
+
+// Resolves a path relative to the directory of another logical path, e.g. an image URL relative to
+// the asset metadata file. Logical paths always use forward slashes.
+pub fn resolve_relative_path(base_path: &str, path: &str) -> String {
+    match base_path.rsplit_once('/') {
+        Some((base_dir, _)) => format!("{base_dir}/{path}"),
+        None => path.to_owned(),
+    }
+}
+
+fn load_data_from_file(path: &Path) -> anyhow::Result<Bytes> {
+    let mut stream = BufReader::new(File::open(path)?);
+    let mut buffer = vec![];
+    stream.read_to_end(&mut buffer)?;
+
+    Ok(Bytes::from(buffer))
+}
+
+// Reads files from a directory on disk. `file://` paths are read as is, bypassing the directory.
+pub struct DirectorySource {
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl AssetSource for DirectorySource {
+    fn read(&self, path: &str) -> anyhow::Result<Bytes> {
+        let path = match path.strip_prefix("file://") {
+            Some(path) => PathBuf::from(path),
+            None if path.contains("://") => anyhow::bail!("not a file path: {path}"),
+            None => self.root.join(path),
+        };
+
+        println!("loading file: {path:?}");
+
+        load_data_from_file(&path)
+    }
+}
+
+// Keeps all the data in memory. Mostly useful for tests.
+#[derive(Default)]
+pub struct MemorySource {
+    files: HashMap<String, Bytes>,
+}
+
+impl MemorySource {
+    pub fn insert(&mut self, path: &str, data: impl Into<Bytes>) {
+        self.files.insert(path.to_owned(), data.into());
+    }
+}
+
+impl AssetSource for MemorySource {
+    fn read(&self, path: &str) -> anyhow::Result<Bytes> {
+        match self.files.get(path) {
+            Some(data) => Ok(data.clone()),
+            None => anyhow::bail!("file not found: {path}"),
+        }
+    }
+}
+
+// Reads files from a single pack file, see `write_pack_file`. The whole pack is loaded into memory
+// when opened.
+pub struct PackSource {
+    files: HashMap<String, Bytes>,
+}
+
+impl PackSource {
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        println!("opening pack file: {path}");
+
+        Self::from_bytes(load_data_from_file(Path::new(path))?)
+    }
+
+    pub fn from_bytes(mut data: Bytes) -> anyhow::Result<Self> {
+        // Pack layout (little endian): magic, version, file count, and then for each file: path
+        // length, path, data length, data.
+        if data.len() < 12 || &data[..4] != PACK_MAGIC {
+            anyhow::bail!("not a pack file");
+        }
+
+        data.advance(4);
+
+        let version = data.get_u32_le();
+        if version != PACK_VERSION {
+            anyhow::bail!("unsupported pack file version: {version}");
+        }
+
+        let count = data.get_u32_le();
+        let mut files = HashMap::new();
+
+        for _ in 0..count {
+            let path = String::from_utf8(read_pack_chunk(&mut data)?.to_vec())?;
+            let file = read_pack_chunk(&mut data)?;
+
+            files.insert(path, file);
+        }
+
+        Ok(Self { files })
+    }
+}
+
+fn read_pack_chunk(data: &mut Bytes) -> anyhow::Result<Bytes> {
+    if data.remaining() < 8 {
+        anyhow::bail!("pack file is truncated");
+    }
+
+    let len = data.get_u64_le() as usize;
+    if data.remaining() < len {
+        anyhow::bail!("pack file is truncated");
+    }
+
+    Ok(data.split_to(len))
+}
+
+impl AssetSource for PackSource {
+    fn read(&self, path: &str) -> anyhow::Result<Bytes> {
+        match self.files.get(path) {
+            Some(data) => Ok(data.clone()),
+            None => anyhow::bail!("file not found in pack: {path}"),
+        }
+    }
+}
+
+pub fn write_pack_file(path: &str, files: &[(String, Bytes)]) -> anyhow::Result<()> {
+    println!("writing pack file: {path} ({} files)", files.len());
+
+    let mut buffer = vec![];
+    buffer.put_slice(PACK_MAGIC);
+    buffer.put_u32_le(PACK_VERSION);
+    buffer.put_u32_le(files.len() as u32);
+
+    for (file_path, data) in files {
+        buffer.put_u64_le(file_path.len() as u64);
+        buffer.put_slice(file_path.as_bytes());
+        buffer.put_u64_le(data.len() as u64);
+        buffer.put_slice(data);
+    }
+
+    let mut stream = BufWriter::new(File::create(path)?);
+    stream.write_all(&buffer)?;
+
+    Ok(())
+}
+
+// Downloads `http://` and `https://` URLs, caching the downloaded data on disk.
+pub struct HttpSource {
+    cache_dir: PathBuf,
+}
+
+impl HttpSource {
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            cache_dir: cache_dir.into(),
+        }
+    }
+}
+
+impl AssetSource for HttpSource {
+    fn read(&self, url: &str) -> anyhow::Result<Bytes> {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            anyhow::bail!("not an http url: {url}");
+        }
+
+        // This wasn't necessary, but resource loading took awfully long time on each program start,
+        // so I thought that this may actually save some development time.
+        let hash = Sha256::new().chain_update(url).finalize();
+        let cache_file = self.cache_dir.join(format!("{:x}.png", hash));
+
+        if cache_file.exists() {
+            println!("loading cached data: {cache_file:?}");
+
+            // Correction: it wasn't slow because of download, but because of PNG decoding... oh well =\
+            load_data_from_file(&cache_file)
+        } else {
+            println!("downloading data: {url}");
+
+            let result = reqwest::blocking::get(url)?.bytes()?;
+            fs::create_dir_all(&self.cache_dir)?;
+            let mut stream = BufWriter::new(File::create(cache_file)?);
+            stream.write_all(&result)?;
+
+            Ok(result)
+        }
+    }
+}
+
+// Tries a list of sources in order, returning data from the first one that succeeds.
+pub struct SourceChain {
+    sources: Vec<Box<dyn AssetSource>>,
+}
+
+impl SourceChain {
+    pub fn new(sources: Vec<Box<dyn AssetSource>>) -> Self {
+        Self { sources }
+    }
+}
+
+impl AssetSource for SourceChain {
+    fn read(&self, path: &str) -> anyhow::Result<Bytes> {
+        let mut errors = vec![];
+
+        for source in &self.sources {
+            match source.read(path) {
+                Ok(data) => return Ok(data),
+                Err(err) => errors.push(err.to_string()),
+            }
+        }
+
+        anyhow::bail!("failed to read {path}: {}", errors.join("; "))
+    }
+}
+
+// Files from the data pack (if there is one in the working directory), then files relative to the
+// working directory, and finally remote images, downloaded and cached.
+pub fn default_asset_source() -> SourceChain {
+    let mut sources: Vec<Box<dyn AssetSource>> = vec![];
+
+    if Path::new(DATA_PACK_FILE).exists() {
+        match PackSource::open(DATA_PACK_FILE) {
+            Ok(pack) => sources.push(Box::new(pack)),
+            Err(err) => println!("failed to open {DATA_PACK_FILE}: {err}"),
+        }
+    }
+
+    sources.push(Box::new(DirectorySource::new("")));
+    sources.push(Box::new(HttpSource::new(CACHE_DIR)));
+
+    SourceChain::new(sources)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            resolve_relative_path("assets/chair.json", "chair/0.png"),
+            "assets/chair/0.png"
+        );
+        assert_eq!(resolve_relative_path("chair.json", "chair/0.png"), "chair/0.png");
+    }
+
+    #[test]
+    fn pack_file_round_trip() {
+        let files = vec![
+            (String::from("assets/chair.json"), Bytes::from_static(b"{}")),
+            (String::from("assets/chair/0.png"), Bytes::from_static(&[0, 1, 2, 3])),
+        ];
+
+        // Unique per process, so that concurrent test runs don't overwrite each other's file.
+        let path = std::env::temp_dir().join(format!("sophya_pack_round_trip_{}.pack", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        write_pack_file(path, &files).unwrap();
+        let pack = PackSource::open(path).unwrap();
+
+        for (file_path, data) in &files {
+            assert_eq!(&pack.read(file_path).unwrap(), data);
+        }
+
+        assert!(pack.read("assets/table.json").is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn source_chain_falls_back() {
+        let mut first = MemorySource::default();
+        first.insert("a", &b"first"[..]);
+
+        let mut second = MemorySource::default();
+        second.insert("a", &b"second"[..]);
+        second.insert("b", &b"second"[..]);
+
+        let chain = SourceChain::new(vec![Box::new(first), Box::new(second)]);
+
+        assert_eq!(chain.read("a").unwrap(), &b"first"[..]);
+        assert_eq!(chain.read("b").unwrap(), &b"second"[..]);
+        assert!(chain.read("c").is_err());
+    }
+}
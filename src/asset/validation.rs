@@ -0,0 +1,739 @@
+use super::*;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fmt};
+
+// A single problem found in asset metadata, along with the JSON path to the offending value.
+#[derive(Debug)]
+pub struct ValidationError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+// Validates raw asset metadata JSON, reporting every problem at once instead of bailing on the first
+// one like deserialization does. Metadata without validation errors is guaranteed to deserialize
+// into `AssetMetadata`.
+pub fn validate_asset_metadata(input: &Value) -> Vec<ValidationError> {
+    let mut validator = Validator::default();
+    validator.asset(input);
+    validator.errors
+}
+
+// Same as `validate_asset_metadata`, for character definitions, see `CharacterMetadata`.
+pub fn validate_character_metadata(input: &Value) -> Vec<ValidationError> {
+    let mut validator = Validator::default();
+    validator.character(input);
+    validator.errors
+}
+
+// Parses and validates asset metadata, failing with every validation problem found.
+pub fn parse_asset_metadata(data: &[u8]) -> anyhow::Result<AssetMetadata> {
+    parse_validated(data, "asset metadata", validate_asset_metadata)
+}
+
+// Parses and validates a character definition, failing with every validation problem found.
+pub fn parse_character_metadata(data: &[u8]) -> anyhow::Result<CharacterMetadata> {
+    parse_validated(data, "character metadata", validate_character_metadata)
+}
+
+fn parse_validated<T: DeserializeOwned>(
+    data: &[u8],
+    what: &str,
+    validate: fn(&Value) -> Vec<ValidationError>,
+) -> anyhow::Result<T> {
+    let input: Value = serde_json::from_slice(data)?;
+    let errors = validate(&input);
+
+    if !errors.is_empty() {
+        anyhow::bail!(
+            "invalid {what} ({} problems):\n{}",
+            errors.len(),
+            format_validation_errors(&errors)
+        );
+    }
+
+    Ok(serde_json::from_value(input)?)
+}
+
+pub fn format_validation_errors(errors: &[ValidationError]) -> String {
+    errors
+        .iter()
+        .map(|err| format!("  {err}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        format!("{path}.{key}")
+    } else {
+        format!("{path}[{key:?}]")
+    }
+}
+
+// A polygon is convex if all of its corners turn the same way. Collinear points are fine.
+fn is_convex_polygon(points: &[Point]) -> bool {
+    let mut sign = 0.0;
+
+    for i in 0..points.len() {
+        let a = points[i].vec2();
+        let b = points[(i + 1) % points.len()].vec2();
+        let c = points[(i + 2) % points.len()].vec2();
+        let cross = (b - a).perp_dot(c - b);
+
+        if cross.abs() <= f32::EPSILON {
+            continue;
+        }
+
+        if sign != 0.0 && cross.signum() != sign {
+            return false;
+        }
+
+        sign = cross.signum();
+    }
+
+    // All the points on the same line aren't much of a polygon either.
+    sign != 0.0
+}
+
+#[derive(Default)]
+struct Validator {
+    errors: Vec<ValidationError>,
+    // Image IDs have to be unique within the whole asset, not just within an orientation.
+    image_ids: HashMap<String, String>,
+}
+
+impl Validator {
+    fn error(&mut self, path: &str, message: impl Into<String>) {
+        self.errors.push(ValidationError {
+            path: path.to_owned(),
+            message: message.into(),
+        });
+    }
+
+    fn object<'a>(&mut self, value: &'a Value, path: &str) -> Option<&'a Map<String, Value>> {
+        let result = value.as_object();
+
+        if result.is_none() {
+            self.error(path, "expected an object");
+        }
+
+        result
+    }
+
+    fn field<'a>(&mut self, object: &'a Map<String, Value>, path: &str, key: &str) -> Option<&'a Value> {
+        let result = object.get(key);
+
+        if result.is_none() {
+            self.error(&join_path(path, key), "missing field");
+        }
+
+        result
+    }
+
+    // Checks that the value deserializes into the given type, e.g. a known enum variant.
+    fn typed<T: DeserializeOwned>(&mut self, value: &Value, path: &str, expected: &str) -> Option<T> {
+        match serde_json::from_value(value.clone()) {
+            Ok(result) => Some(result),
+            Err(_) => {
+                self.error(path, format!("expected {expected}, found {value}"));
+                None
+            }
+        }
+    }
+
+    fn point(&mut self, object: &Map<String, Value>, path: &str, key: &str) -> Option<Point> {
+        let value = self.field(object, path, key)?;
+        self.typed(value, &join_path(path, key), "a point ({\"x\": number, \"y\": number})")
+    }
+
+    fn scale(&mut self, object: &Map<String, Value>, path: &str) -> Option<Point> {
+        let scale = self.point(object, path, "scale")?;
+
+        if scale.x <= 0.0 || scale.y <= 0.0 {
+            self.error(
+                &join_path(path, "scale"),
+                format!("scale must be positive, found {}, {}", scale.x, scale.y),
+            );
+            return None;
+        }
+
+        Some(scale)
+    }
+
+    fn asset(&mut self, value: &Value) {
+        let path = "$";
+        let asset = match self.object(value, path) {
+            Some(asset) => asset,
+            None => return,
+        };
+
+        if let Some(name) = self.field(asset, path, "name") {
+            self.typed::<String>(name, &join_path(path, "name"), "a string");
+        }
+
+        if let Some(opacity) = asset.get("shadowOpacity") {
+            let opacity_path = join_path(path, "shadowOpacity");
+
+            if let Some(opacity) = self.typed::<f32>(opacity, &opacity_path, "a number") {
+                if !(0.0..=1.0).contains(&opacity) {
+                    self.error(&opacity_path, format!("opacity should be within 0..1, found {opacity}"));
+                }
+            }
+        }
+
+        let orientations_path = join_path(path, "orientations");
+        let orientations = match self.field(asset, path, "orientations") {
+            Some(orientations) => self.object(orientations, &orientations_path),
+            None => None,
+        };
+
+        for (key, orientation) in orientations.into_iter().flatten() {
+            let orientation_path = join_path(&orientations_path, key);
+
+            let orientation_key = self.typed::<AssetOrientation>(
+                &Value::String(key.clone()),
+                &orientation_path,
+                "an orientation (N, E, S or W)",
+            );
+
+            if orientation_key.map_or(false, AssetOrientation::is_diagonal) {
+                self.error(
+                    &orientation_path,
+                    "assets can only face N, E, S or W, diagonal orientations are for characters",
+                );
+            }
+
+            self.orientation(orientation, &orientation_path);
+        }
+    }
+
+    fn orientation(&mut self, value: &Value, path: &str) {
+        let orientation = match self.object(value, path) {
+            Some(orientation) => orientation,
+            None => return,
+        };
+
+        let images_path = join_path(path, "images");
+        let images = match self.field(orientation, path, "images") {
+            Some(images) => self.object(images, &images_path),
+            None => None,
+        };
+
+        match images {
+            Some(images) if images.is_empty() => self.error(&images_path, "orientation has no images"),
+            Some(images) => {
+                for (id, image) in images {
+                    self.image(id, image, &join_path(&images_path, id));
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn image(&mut self, id: &str, value: &Value, path: &str) {
+        if let Some(first_path) = self.image_ids.insert(id.to_owned(), path.to_owned()) {
+            self.error(path, format!("duplicate image ID, first used at {first_path}"));
+        }
+
+        let image = match self.object(value, path) {
+            Some(image) => image,
+            None => return,
+        };
+
+        if let Some(ty) = self.field(image, path, "type") {
+            self.typed::<AssetImageType>(ty, &join_path(path, "type"), "\"image\"");
+        }
+
+        if let Some(url) = self.field(image, path, "url") {
+            self.typed::<String>(url, &join_path(path, "url"), "a string");
+        }
+
+        let transform_path = join_path(path, "transform");
+        if let Some(transform) = self.field(image, path, "transform") {
+            if let Some(transform) = self.object(transform, &transform_path) {
+                self.point(transform, &transform_path, "position");
+                self.scale(transform, &transform_path);
+                self.point(transform, &transform_path, "frontPoint");
+            }
+        }
+
+        let primitives_path = join_path(path, "primitives");
+        let primitives = match self.field(image, path, "primitives") {
+            Some(primitives) => self.object(primitives, &primitives_path),
+            None => None,
+        };
+
+        for (id, primitive) in primitives.into_iter().flatten() {
+            self.primitive(primitive, &join_path(&primitives_path, id));
+        }
+
+        if let Some(animation) = image.get("animation") {
+            self.animation(animation, &join_path(path, "animation"));
+        }
+    }
+
+    fn animation(&mut self, value: &Value, path: &str) {
+        let animation = self.typed::<AnimationClipData>(value, path, "an animation clip");
+
+        if let Some(Err(err)) = animation.map(|x| x.validate()) {
+            self.error(path, err.to_string());
+        }
+    }
+
+    fn height(&mut self, object: &Map<String, Value>, path: &str) {
+        if let Some(height) = self.field(object, path, "height") {
+            let height_path = join_path(path, "height");
+
+            if let Some(height) = self.typed::<f32>(height, &height_path, "a number") {
+                if height <= 0.0 {
+                    self.error(&height_path, format!("height must be positive, found {height}"));
+                }
+            }
+        }
+    }
+
+    fn shape(&mut self, shape: &Map<String, Value>, path: &str) {
+        self.point(shape, path, "position");
+        let scale = self.scale(shape, path);
+
+        let shape_type = match self.field(shape, path, "shape") {
+            Some(shape_type) => {
+                self.typed::<AssetShapeType>(shape_type, &join_path(path, "shape"), "a known shape type")
+            }
+            None => None,
+        };
+
+        let points_path = join_path(path, "points");
+
+        match shape_type {
+            Some(AssetShapeType::Polygon) => {
+                let points = match self.field(shape, path, "points") {
+                    Some(points) => self.typed::<Vec<Point>>(points, &points_path, "a list of points"),
+                    None => None,
+                };
+
+                if let Some(points) = points {
+                    self.polygon(&points, &points_path);
+                }
+            }
+            Some(_) if shape.contains_key("points") => self.error(&points_path, "only polygons can have points"),
+            _ => {}
+        }
+
+        if let (Some(AssetShapeType::Circle), Some(scale)) = (shape_type, scale) {
+            if (scale.x - scale.y).abs() > f32::EPSILON * scale.x.max(scale.y) {
+                self.error(
+                    &join_path(path, "scale"),
+                    format!(
+                        "circles must have equal scale, found {}, {} (use an ellipse instead)",
+                        scale.x, scale.y
+                    ),
+                );
+            }
+        }
+    }
+
+    fn polygon(&mut self, points: &[Point], path: &str) {
+        if points.len() < 3 {
+            self.error(path, format!("polygons need at least 3 points, found {}", points.len()));
+            return;
+        }
+
+        for (i, point) in points.iter().enumerate() {
+            if !(0.0..=1.0).contains(&point.x) || !(0.0..=1.0).contains(&point.y) {
+                self.error(
+                    &format!("{path}[{i}]"),
+                    format!(
+                        "points are normalized to the shape bounds and should be within 0..1, found {}, {}",
+                        point.x, point.y
+                    ),
+                );
+            }
+        }
+
+        if !is_convex_polygon(points) {
+            self.error(
+                path,
+                "polygons must be convex, split concave shapes into several primitives",
+            );
+        }
+    }
+
+    fn primitive(&mut self, value: &Value, path: &str) {
+        let primitive = match self.object(value, path) {
+            Some(primitive) => primitive,
+            None => return,
+        };
+
+        let ty = match self.field(primitive, path, "type") {
+            Some(ty) => self.typed::<AssetPrimitiveType>(ty, &join_path(path, "type"), "\"shadow\" or \"collider\""),
+            None => None,
+        };
+
+        let shape_path = join_path(path, "shape");
+        if let Some(shape) = self.field(primitive, path, "shape") {
+            if let Some(shape) = self.object(shape, &shape_path) {
+                self.shape(shape, &shape_path);
+            }
+        }
+
+        match ty {
+            Some(AssetPrimitiveType::Collider) => {
+                if primitive.contains_key("shadowType") {
+                    self.error(&join_path(path, "shadowType"), "colliders can't have a shadow type");
+                }
+
+                if primitive.contains_key("softness") {
+                    self.error(&join_path(path, "softness"), "colliders can't have softness");
+                }
+
+                self.height(primitive, path);
+            }
+            Some(AssetPrimitiveType::Shadow) => {
+                if primitive.contains_key("height") {
+                    self.error(&join_path(path, "height"), "shadows can't have a height");
+                }
+
+                if let Some(shadow_type) = self.field(primitive, path, "shadowType") {
+                    self.typed::<AssetShapeType>(shadow_type, &join_path(path, "shadowType"), "a known shape type");
+                }
+
+                if let Some(softness) = primitive.get("softness") {
+                    let softness_path = join_path(path, "softness");
+
+                    if let Some(softness) = self.typed::<f32>(softness, &softness_path, "a number") {
+                        if softness < 0.0 {
+                            self.error(&softness_path, format!("softness can't be negative, found {softness}"));
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn character(&mut self, value: &Value) {
+        let path = "$";
+        let character = match self.object(value, path) {
+            Some(character) => character,
+            None => return,
+        };
+
+        if let Some(name) = self.field(character, path, "name") {
+            self.typed::<String>(name, &join_path(path, "name"), "a string");
+        }
+
+        if let Some(speed) = self.field(character, path, "movementSpeed") {
+            let speed_path = join_path(path, "movementSpeed");
+
+            if let Some(speed) = self.typed::<f32>(speed, &speed_path, "a number") {
+                if speed <= 0.0 {
+                    self.error(&speed_path, format!("movement speed must be positive, found {speed}"));
+                }
+            }
+        }
+
+        let directions_path = join_path(path, "directions");
+        let directions = match self.field(character, path, "directions") {
+            Some(directions) => self.object(directions, &directions_path),
+            None => None,
+        };
+
+        match directions {
+            Some(directions) if directions.is_empty() => self.error(&directions_path, "character has no images"),
+            Some(directions) => {
+                for (key, image) in directions {
+                    let image_path = join_path(&directions_path, key);
+
+                    self.typed::<AssetOrientation>(
+                        &Value::String(key.clone()),
+                        &image_path,
+                        "an orientation (N, E, S, W, NE, SE, SW or NW)",
+                    );
+                    self.character_image(image, &image_path);
+                }
+            }
+            None => {}
+        }
+
+        let collider_path = join_path(path, "collider");
+        if let Some(collider) = self.field(character, path, "collider") {
+            if let Some(collider) = self.object(collider, &collider_path) {
+                self.character_collider(collider, &collider_path);
+            }
+        }
+    }
+
+    fn character_image(&mut self, value: &Value, path: &str) {
+        let image = match self.object(value, path) {
+            Some(image) => image,
+            None => return,
+        };
+
+        if let Some(url) = self.field(image, path, "url") {
+            self.typed::<String>(url, &join_path(path, "url"), "a string");
+        }
+
+        let transform_path = join_path(path, "transform");
+        if let Some(transform) = self.field(image, path, "transform") {
+            if let Some(transform) = self.object(transform, &transform_path) {
+                self.point(transform, &transform_path, "position");
+                self.point(transform, &transform_path, "frontPoint");
+            }
+        }
+
+        if let Some(clips) = image.get("clips") {
+            let clips_path = join_path(path, "clips");
+
+            for (name, clip) in self.object(clips, &clips_path).into_iter().flatten() {
+                self.animation(clip, &join_path(&clips_path, name));
+            }
+        }
+    }
+
+    fn character_collider(&mut self, collider: &Map<String, Value>, path: &str) {
+        let shape_path = join_path(path, "shape");
+        if let Some(shape) = self.field(collider, path, "shape") {
+            if let Some(shape) = self.object(shape, &shape_path) {
+                self.shape(shape, &shape_path);
+
+                // Character bodies are cuboids, see `spawn_character`. Unknown shape types are
+                // already reported by `shape`.
+                let shape_type = shape
+                    .get("shape")
+                    .cloned()
+                    .map(serde_json::from_value::<AssetShapeType>);
+
+                if let Some(Ok(shape_type)) = shape_type {
+                    if shape_type != AssetShapeType::Rect {
+                        self.error(&join_path(&shape_path, "shape"), "character colliders must be rects");
+                    }
+                }
+            }
+        }
+
+        self.height(collider, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_paths(input: &str) -> Vec<String> {
+        let mut paths = validate_asset_metadata(&serde_json::from_str(input).unwrap())
+            .into_iter()
+            .map(|err| err.path)
+            .collect::<Vec<_>>();
+
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn shipped_assets_are_valid() {
+        for path in ["assets/chair.json", "assets/table.json", "assets/bookshelf.json"] {
+            let input = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+            let errors = validate_asset_metadata(&input);
+
+            assert!(errors.is_empty(), "{path}:\n{}", format_validation_errors(&errors));
+        }
+    }
+
+    #[test]
+    fn shipped_character_is_valid() {
+        let input = serde_json::from_slice(&std::fs::read("assets/character.json").unwrap()).unwrap();
+        let errors = validate_character_metadata(&input);
+
+        assert!(errors.is_empty(), "{}", format_validation_errors(&errors));
+    }
+
+    #[test]
+    fn reports_every_character_problem() {
+        let input = r#"{
+            "name": "Broken",
+            "movementSpeed": 0,
+            "directions": {
+                "S": {
+                    "url": "character.png",
+                    "transform": { "position": { "x": 0, "y": 0 } },
+                    "clips": { "walk": { "frames": [] } }
+                },
+                "UP": {
+                    "url": "character.png",
+                    "transform": { "position": { "x": 0, "y": 0 }, "frontPoint": { "x": 0, "y": 0 } }
+                }
+            },
+            "collider": {
+                "shape": { "position": { "x": 0, "y": 0 }, "scale": { "x": 10, "y": 10 }, "shape": "circle" },
+                "height": -1
+            }
+        }"#;
+
+        let mut paths = validate_character_metadata(&serde_json::from_str(input).unwrap())
+            .into_iter()
+            .map(|err| err.path)
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        assert_eq!(
+            paths,
+            vec![
+                "$.collider.height",
+                "$.collider.shape.shape",
+                "$.directions.S.clips.walk",
+                "$.directions.S.transform.frontPoint",
+                "$.directions.UP",
+                "$.movementSpeed",
+            ]
+        );
+    }
+
+    fn shape_error_paths(shape: &str) -> Vec<String> {
+        let input = r#"{
+            "name": "Shapes",
+            "orientations": {
+                "N": {
+                    "images": {
+                        "a": {
+                            "type": "image",
+                            "url": "a.png",
+                            "transform": {
+                                "position": { "x": 0, "y": 0 },
+                                "scale": { "x": 1, "y": 1 },
+                                "frontPoint": { "x": 0, "y": 0 }
+                            },
+                            "primitives": {
+                                "c": { "type": "collider", "height": 10, "shape": SHAPE }
+                            }
+                        }
+                    }
+                }
+            }
+        }"#;
+
+        error_paths(&input.replace("SHAPE", shape))
+    }
+
+    #[test]
+    fn validates_shapes() {
+        let shape = |rest: &str| format!(r#"{{ "position": {{ "x": 0, "y": 0 }}, {rest} }}"#);
+        let prefix = "$.orientations.N.images.a.primitives.c.shape";
+
+        assert!(shape_error_paths(&shape(r#""scale": { "x": 10, "y": 10 }, "shape": "circle""#)).is_empty());
+        assert!(shape_error_paths(&shape(r#""scale": { "x": 10, "y": 20 }, "shape": "ellipse""#)).is_empty());
+        assert!(shape_error_paths(&shape(
+            r#""scale": { "x": 10, "y": 20 }, "shape": "polygon",
+               "points": [{ "x": 0.5, "y": 0 }, { "x": 1, "y": 0.5 }, { "x": 0.5, "y": 1 }, { "x": 0, "y": 0.5 }]"#
+        ))
+        .is_empty());
+
+        assert_eq!(
+            shape_error_paths(&shape(r#""scale": { "x": 10, "y": 20 }, "shape": "circle""#)),
+            vec![format!("{prefix}.scale")]
+        );
+        assert_eq!(
+            shape_error_paths(&shape(
+                r#""scale": { "x": 10, "y": 20 }, "shape": "rect", "points": []"#
+            )),
+            vec![format!("{prefix}.points")]
+        );
+        assert_eq!(
+            shape_error_paths(&shape(r#""scale": { "x": 10, "y": 20 }, "shape": "polygon""#)),
+            vec![format!("{prefix}.points")]
+        );
+
+        // L-shaped, i.e. concave, with one of the points out of bounds.
+        assert_eq!(
+            shape_error_paths(&shape(
+                r#""scale": { "x": 10, "y": 20 }, "shape": "polygon",
+                   "points": [{ "x": 0, "y": 0 }, { "x": 1, "y": 0 }, { "x": 1, "y": 0.5 },
+                              { "x": 0.5, "y": 0.5 }, { "x": 0.5, "y": 1.5 }, { "x": 0, "y": 1 }]"#
+            )),
+            vec![format!("{prefix}.points"), format!("{prefix}.points[4]")]
+        );
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let input = r#"{
+            "name": "Broken",
+            "orientations": {
+                "N": {
+                    "images": {
+                        "a": {
+                            "type": "image",
+                            "url": "a.png",
+                            "transform": {
+                                "position": { "x": 0, "y": 0 },
+                                "scale": { "x": 0, "y": 1 },
+                                "frontPoint": { "x": 0, "y": 0 }
+                            },
+                            "primitives": {
+                                "c": {
+                                    "type": "collider",
+                                    "shadowType": "rect",
+                                    "shape": {
+                                        "position": { "x": 0, "y": 0 },
+                                        "scale": { "x": 10, "y": -10 },
+                                        "shape": "hexagon"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "E": {
+                    "images": {
+                        "a": {
+                            "type": "image",
+                            "url": "a.png",
+                            "transform": {
+                                "position": { "x": 0, "y": 0 },
+                                "scale": { "x": 1, "y": 1 },
+                                "frontPoint": { "x": 0, "y": 0 }
+                            },
+                            "primitives": {}
+                        }
+                    }
+                },
+                "S": { "images": {} },
+                "NE": {
+                    "images": {
+                        "b": {
+                            "type": "image",
+                            "url": "b.png",
+                            "transform": {
+                                "position": { "x": 0, "y": 0 },
+                                "scale": { "x": 1, "y": 1 },
+                                "frontPoint": { "x": 0, "y": 0 }
+                            },
+                            "primitives": {}
+                        }
+                    }
+                }
+            }
+        }"#;
+
+        assert_eq!(
+            error_paths(input),
+            vec![
+                "$.orientations.E.images.a",
+                "$.orientations.N.images.a.primitives.c.height",
+                "$.orientations.N.images.a.primitives.c.shadowType",
+                "$.orientations.N.images.a.primitives.c.shape.scale",
+                "$.orientations.N.images.a.primitives.c.shape.shape",
+                "$.orientations.N.images.a.transform.scale",
+                "$.orientations.NE",
+                "$.orientations.S.images",
+            ]
+        );
+    }
+}
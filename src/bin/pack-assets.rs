@@ -0,0 +1,23 @@
+use sophya_rust_challenge::asset::{default_asset_source, write_pack_file, AssetSource};
+
+// Packs files into a single data file, readable with `PackSource`. Files are stored under the
+// paths they're given with, so pack them relative to the working directory, the same way scene
+// files refer to them.
+//
+// Usage: pack-assets <output.pack> <file>...
+fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.len() < 2 {
+        anyhow::bail!("usage: pack-assets <output.pack> <file>...");
+    }
+
+    let source = default_asset_source();
+    let mut files = vec![];
+
+    for path in &args[1..] {
+        files.push((path.replace('\\', "/"), source.read(path)?));
+    }
+
+    write_pack_file(&args[0], &files)
+}
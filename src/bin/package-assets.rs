@@ -0,0 +1,24 @@
+use sophya_rust_challenge::asset::{default_asset_source, package_asset};
+use std::path::Path;
+
+// Packages assets for offline use: downloads all of their images into a local directory, so that
+// the packaged assets can be loaded without network access.
+//
+// Usage: package-assets <output dir> <asset.json>...
+fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.len() < 2 {
+        anyhow::bail!("usage: package-assets <output dir> <asset.json>...");
+    }
+
+    let source = default_asset_source();
+    let output_dir = Path::new(&args[0]);
+
+    for asset in &args[1..] {
+        let output_path = package_asset(&source, asset, output_dir)?;
+        println!("packaged {asset} into {output_path:?}");
+    }
+
+    Ok(())
+}
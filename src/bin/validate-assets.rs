@@ -0,0 +1,52 @@
+use serde_json::Value;
+use sophya_rust_challenge::asset::{validate_asset_metadata, validate_character_metadata, ValidationError};
+
+// Character files are told apart from assets by their `directions`, which assets don't have.
+fn validate(input: &Value) -> Vec<ValidationError> {
+    if input.get("directions").is_some() {
+        validate_character_metadata(input)
+    } else {
+        validate_asset_metadata(input)
+    }
+}
+
+// Validates asset and character metadata files, reporting every problem found along with its JSON
+// path. Meant to be run before committing new or edited assets.
+//
+// Usage: validate-assets <asset.json>...
+fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.is_empty() {
+        anyhow::bail!("usage: validate-assets <asset.json>...");
+    }
+
+    let mut invalid_count = 0;
+
+    for path in &args {
+        let errors = match std::fs::read(path) {
+            Ok(data) => match serde_json::from_slice(&data) {
+                Ok(input) => validate(&input).iter().map(|err| err.to_string()).collect::<Vec<_>>(),
+                Err(err) => vec![format!("malformed JSON: {err}")],
+            },
+            Err(err) => vec![format!("failed to read: {err}")],
+        };
+
+        if errors.is_empty() {
+            println!("{path}: ok");
+        } else {
+            invalid_count += 1;
+            println!("{path}: {} problems", errors.len());
+
+            for err in errors {
+                println!("  {err}");
+            }
+        }
+    }
+
+    if invalid_count > 0 {
+        anyhow::bail!("{invalid_count} of {} assets are invalid", args.len());
+    }
+
+    Ok(())
+}
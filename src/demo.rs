@@ -1,8 +1,14 @@
-use crate::{asset::*, scene::*};
+use crate::{asset::*, math::*, render::*, scene::*, ui::*};
 use macroquad::prelude::*;
+use std::sync::Arc;
 
-const PLAYER_MOVEMENT_SPEED: f32 = 200.0;
-const DEMO_SCENE_SCALE: f32 = 0.25;
+const DEBUG_MENU_KEY: KeyCode = KeyCode::F1;
+const LEVEL_EDITOR_KEY: KeyCode = KeyCode::F2;
+const DEMO_SCENE_PATH: &str = "assets/demo_scene.json";
+const CAMERA_PAN_BUTTON: MouseButton = MouseButton::Middle;
+
+// Switching scenes fades the view to black and back in this long, each way, in seconds.
+const SCENE_FADE_DURATION: f32 = 0.3;
 
 pub fn get_movement_input() -> Vec2 {
     let mut movement_dir = vec2(0.0, 0.0);
@@ -26,70 +32,204 @@ pub fn get_movement_input() -> Vec2 {
     movement_dir.normalize_or_zero()
 }
 
+// Mouse wheel zooms, dragging with the middle mouse button pans. Panning stops the camera from
+// following the player until it moves again.
+fn update_camera_input(camera: &mut SceneCamera, last_mouse_position: Vec2, pointer_captured: bool, moving: bool) {
+    if moving {
+        camera.following = true;
+    }
+
+    if pointer_captured {
+        return;
+    }
+
+    let (_, wheel) = mouse_wheel();
+    if wheel != 0.0 {
+        // Wheel deltas differ a lot between platforms, so only the direction is used.
+        camera.zoom_by(wheel.signum());
+    }
+
+    if is_mouse_button_down(CAMERA_PAN_BUTTON) {
+        camera.pan(Vec2::from(mouse_position()) - last_mouse_position);
+    }
+}
+
 pub struct DemoScene {
     scene: Scene,
+    debug_menu: DebugMenu,
+    level_editor: LevelEditor,
+    last_mouse_position: Vec2,
+    // Scene a doorway leads to, along with its path, while it's being loaded.
+    next_scene: Option<(SceneLoader, String)>,
+    // How far the view is faded to black, from 0 to 1.
+    fade: f32,
+}
+
+pub fn draw_loading_screen(progress: f32) {
+    const BAR_WIDTH: f32 = 400.0;
+    const BAR_HEIGHT: f32 = 16.0;
+
+    clear_background(BLACK);
+
+    let x = (screen_width() - BAR_WIDTH) * 0.5;
+    let y = (screen_height() - BAR_HEIGHT) * 0.5;
+
+    draw_text("loading...", x, y - 10.0, 24.0, WHITE);
+    draw_rectangle(x, y, BAR_WIDTH * progress, BAR_HEIGHT, WHITE);
+    draw_rectangle_lines(x, y, BAR_WIDTH, BAR_HEIGHT, 2.0, WHITE);
+}
+
+fn draw_fade(fade: f32) {
+    if fade > 0.0 {
+        let color = Color::new(0.0, 0.0, 0.0, fade);
+        draw_rectangle(0.0, 0.0, screen_width(), screen_height(), color);
+    }
 }
 
 impl DemoScene {
-    pub fn new() -> anyhow::Result<Self> {
-        // This is here since we don't have asset metadata available for the character.
-        let player_spawn_params = CharacterSpawnParams {
-            offset: vec2(0.0, 160.0),
-            front_point: vec2(-150.0, -150.0),
-            texture: load_texture_from_file("assets/character.png")?,
-            position: vec2(500.0, 200.0),
-            physics_body_origin: vec3(40.0, 40.0, 160.0),
-            physics_collider_half_extent: vec3(40.0, 40.0, 160.0),
+    // The demo room is authored as data, see `assets/demo_scene.json`. It's loaded in the background,
+    // see `SceneLoader`.
+    pub fn start_loading() -> anyhow::Result<SceneLoader> {
+        SceneLoader::start(Arc::new(default_asset_source()), DEMO_SCENE_PATH)
+    }
+
+    pub fn new(scene: Scene) -> Self {
+        Self {
+            scene,
+            debug_menu: DebugMenu::default(),
+            level_editor: LevelEditor::new(DEMO_SCENE_PATH),
+            last_mouse_position: Vec2::from(mouse_position()),
+            next_scene: None,
+            fade: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.update_scene_switch(dt);
+
+        // The player stays put while switching scenes, and while editing the level, so that editor
+        // keys don't move it around.
+        let switching = self.next_scene.is_some();
+        let movement_input = if switching || self.level_editor.enabled {
+            Vec2::ZERO
+        } else {
+            get_movement_input()
+        };
+        self.scene.set_player_movement_input(movement_input);
+        self.scene.update(dt);
+
+        if is_key_pressed(DEBUG_MENU_KEY) {
+            self.debug_menu.toggle();
+        }
+
+        if is_key_pressed(LEVEL_EDITOR_KEY) {
+            self.level_editor.toggle();
+        }
+
+        let debug_menu = &mut self.debug_menu;
+        let level_editor = &mut self.level_editor;
+        let scene = &mut self.scene;
+        let mut pointer_captured = false;
+        let mut keyboard_captured = false;
+
+        egui_macroquad::ui(|egui_ctx| {
+            debug_menu.ui(egui_ctx, scene);
+
+            if let Err(err) = level_editor.ui(egui_ctx, scene) {
+                level_editor.report_error(err);
+            }
+
+            pointer_captured = egui_ctx.wants_pointer_input();
+            keyboard_captured = egui_ctx.wants_keyboard_input();
+        });
+
+        if let Err(err) = level_editor.update(scene, pointer_captured, keyboard_captured) {
+            level_editor.report_error(err);
+        }
+
+        // Clicking on the floor walks the player there, unless the editor uses clicks for itself.
+        let mut moving = movement_input != Vec2::ZERO;
+
+        if !pointer_captured && !level_editor.enabled && !switching && is_mouse_button_pressed(MouseButton::Left) {
+            let target = screen_to_world(scene.camera().window_to_screen(Vec2::from(mouse_position())));
+
+            if scene.move_player_to(target) {
+                moving = true;
+            } else {
+                println!("can't walk to {target:?}");
+            }
+        }
+
+        update_camera_input(scene.camera_mut(), self.last_mouse_position, pointer_captured, moving);
+        self.last_mouse_position = Vec2::from(mouse_position());
+
+        draw_fade(self.fade);
+        egui_macroquad::draw();
+    }
+
+    // Starts loading the scene behind a doorway once the player walks into it, and switches to it
+    // when it's loaded and the view has faded out.
+    fn update_scene_switch(&mut self, dt: f32) {
+        if self.next_scene.is_none() {
+            if let Some(transition) = self.scene.take_transition() {
+                if let Err(err) = self.start_scene_switch(&transition) {
+                    println!("can't switch to {}: {err}", transition.scene);
+                }
+            }
+        }
+
+        let loader = match &mut self.next_scene {
+            Some((loader, _)) => loader,
+            None => {
+                self.fade = (self.fade - dt / SCENE_FADE_DURATION).max(0.0);
+                return;
+            }
+        };
+
+        self.fade = (self.fade + dt / SCENE_FADE_DURATION).min(1.0);
+
+        let loaded = match loader.poll() {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                println!("scene loading error: {err}");
+                self.next_scene = None;
+                return;
+            }
         };
 
-        let mut scene = Scene::new(DEMO_SCENE_SCALE, Some(load_texture_from_file("assets/map.png")?));
-        scene.spawn_player(player_spawn_params);
+        if !loaded || self.fade < 1.0 {
+            return;
+        }
 
-        Ok(Self { scene })
+        if let Some((loader, path)) = self.next_scene.take() {
+            match loader.finish() {
+                Ok(scene) => self.switch_scene(scene, &path),
+                Err(err) => println!("scene loading error: {err}"),
+            }
+        }
     }
 
-    pub fn populate_scene(&mut self) -> anyhow::Result<()> {
-        // Set up the demo scene. This would be loaded from some kind of scene file. For the purpose
-        // of this demo though we spawn hardcoded objects.
-
-        let assets = ["assets/chair.json", "assets/table.json", "assets/bookshelf.json"];
-        let assets = load_asset_bundle(&assets)?;
-
-        // Chairs
-        self.scene
-            .spawn_static_object(&assets[0], vec2(400.0, 20.0), AssetOrientation::East)?;
-        self.scene
-            .spawn_static_object(&assets[0], vec2(500.0, 20.0), AssetOrientation::West)?;
-        self.scene
-            .spawn_static_object(&assets[0], vec2(400.0, 120.0), AssetOrientation::East)?;
-        self.scene
-            .spawn_static_object(&assets[0], vec2(500.0, 120.0), AssetOrientation::West)?;
-
-        // Tables
-        self.scene
-            .spawn_static_object(&assets[1], vec2(440.0, 5.0), AssetOrientation::East)?;
-        self.scene
-            .spawn_static_object(&assets[1], vec2(440.0, 105.0), AssetOrientation::East)?;
-
-        // Bookshelves
-        self.scene
-            .spawn_static_object(&assets[2], vec2(350.0, -30.0), AssetOrientation::East)?;
-        self.scene
-            .spawn_static_object(&assets[2], vec2(350.0, 80.0), AssetOrientation::East)?;
-        self.scene
-            .spawn_static_object(&assets[2], vec2(400.0, -80.0), AssetOrientation::South)?;
-        self.scene
-            .spawn_static_object(&assets[2], vec2(510.0, -80.0), AssetOrientation::South)?;
-
-        // Finish scene 'loading'.
-        self.scene.initialize();
+    fn start_scene_switch(&mut self, transition: &SceneTransition) -> anyhow::Result<()> {
+        let player_state = match self.scene.player_state() {
+            Some(player_state) => player_state,
+            None => anyhow::bail!("scene has no player"),
+        };
+
+        let loader = SceneLoader::start_transition(Arc::new(default_asset_source()), transition, player_state)?;
+        self.next_scene = Some((loader, transition.scene.clone()));
 
         Ok(())
     }
 
-    pub fn update(&mut self, dt: f32) {
-        self.scene
-            .set_player_movement_input(get_movement_input(), PLAYER_MOVEMENT_SPEED);
-        self.scene.update(dt);
+    // Replaces the current scene, which unloads it along with its textures.
+    fn switch_scene(&mut self, scene: Scene, path: &str) {
+        println!("entering scene: {path}");
+
+        // Keep the view zoomed in or out as it was.
+        let zoom = self.scene.camera().zoom;
+        self.scene = scene;
+        self.scene.camera_mut().zoom = zoom;
+
+        self.level_editor.set_scene(path);
     }
 }
@@ -0,0 +1,8 @@
+pub mod asset;
+pub mod demo;
+pub mod math;
+pub mod navigation;
+pub mod physics;
+pub mod render;
+pub mod scene;
+pub mod ui;
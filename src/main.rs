@@ -1,22 +1,27 @@
-mod asset;
-mod demo;
-mod math;
-mod physics;
-mod render;
-mod scene;
-
-use demo::DemoScene;
 use macroquad::prelude::*;
+use sophya_rust_challenge::demo::{draw_loading_screen, DemoScene};
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "sophya-rust-challenge".to_owned(),
+        // Render at the display's native resolution, the scene camera takes care of the DPI scale.
+        high_dpi: true,
+        ..Default::default()
+    }
+}
 
-#[macroquad::main("sophya-rust-challenge")]
+#[macroquad::main(window_conf)]
 async fn main() -> anyhow::Result<()> {
-    println!("creating demo scene...");
+    println!("loading demo scene...");
 
-    let mut demo = DemoScene::new()?;
+    let mut loader = DemoScene::start_loading()?;
 
-    println!("populating world...");
+    while !loader.poll()? {
+        draw_loading_screen(loader.progress());
+        next_frame().await;
+    }
 
-    demo.populate_scene()?;
+    let mut demo = DemoScene::new(loader.finish()?);
 
     println!("entering game loop...");
 
@@ -0,0 +1,266 @@
+use macroquad::prelude::*;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    convert::TryFrom,
+};
+
+// A* costs of moving to a neighbouring cell, straight and diagonally. Integers keep the open set
+// ordering exact.
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+pub type NavCell = (usize, usize);
+
+// Walkability of the ground plane, sampled on a regular grid in world space. Each cell counts the
+// obstacles covering it, so that obstacles can be added and removed independently.
+pub struct NavGrid {
+    // World position of the corner of the first cell.
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub width: usize,
+    pub height: usize,
+    blockers: Vec<u16>,
+}
+
+impl NavGrid {
+    // Grid covering the given world-space rect, with every cell walkable.
+    pub fn new(min: Vec2, max: Vec2, cell_size: f32) -> Self {
+        let size = ((max - min) / cell_size).ceil().max(Vec2::ONE);
+        let width = size.x as usize;
+        let height = size.y as usize;
+
+        Self {
+            origin: min,
+            cell_size,
+            width,
+            height,
+            blockers: vec![0; width * height],
+        }
+    }
+
+    // Makes every cell walkable again.
+    pub fn clear(&mut self) {
+        self.blockers.iter_mut().for_each(|x| *x = 0);
+    }
+
+    // Blocks the cells within the given world-space bounds whose centers are covered by an
+    // obstacle.
+    pub fn add_blocker(&mut self, min: Vec2, max: Vec2, covers: impl Fn(Vec2) -> bool) {
+        self.update_blockers(min, max, covers, |count| count.saturating_add(1));
+    }
+
+    // Reverts `add_blocker` called with the same arguments.
+    pub fn remove_blocker(&mut self, min: Vec2, max: Vec2, covers: impl Fn(Vec2) -> bool) {
+        self.update_blockers(min, max, covers, |count| count.saturating_sub(1));
+    }
+
+    fn update_blockers(&mut self, min: Vec2, max: Vec2, covers: impl Fn(Vec2) -> bool, update: impl Fn(u16) -> u16) {
+        let from = ((min - self.origin) / self.cell_size).floor().max(Vec2::ZERO);
+        let to = ((max - self.origin) / self.cell_size).ceil();
+
+        for y in from.y as usize..(to.y.max(0.0) as usize).min(self.height) {
+            for x in from.x as usize..(to.x.max(0.0) as usize).min(self.width) {
+                if covers(self.cell_center((x, y))) {
+                    let count = &mut self.blockers[y * self.width + x];
+                    *count = update(*count);
+                }
+            }
+        }
+    }
+
+    // Centers of all the cells, along with whether they're walkable.
+    pub fn cells(&self) -> impl Iterator<Item = (Vec2, bool)> + '_ {
+        (0..self.height)
+            .flat_map(move |y| (0..self.width).map(move |x| (self.cell_center((x, y)), self.is_cell_walkable((x, y)))))
+    }
+
+    pub fn cell_at(&self, point: Vec2) -> Option<NavCell> {
+        let cell = ((point - self.origin) / self.cell_size).floor();
+
+        if cell.x < 0.0 || cell.y < 0.0 || cell.x as usize >= self.width || cell.y as usize >= self.height {
+            return None;
+        }
+
+        Some((cell.x as usize, cell.y as usize))
+    }
+
+    pub fn cell_center(&self, (x, y): NavCell) -> Vec2 {
+        self.origin + (vec2(x as f32, y as f32) + 0.5) * self.cell_size
+    }
+
+    pub fn is_cell_walkable(&self, (x, y): NavCell) -> bool {
+        x < self.width && y < self.height && self.blockers[y * self.width + x] == 0
+    }
+
+    pub fn is_walkable(&self, point: Vec2) -> bool {
+        self.cell_at(point).map_or(false, |cell| self.is_cell_walkable(cell))
+    }
+
+    // Walkable neighbours of the cell, along with the cost of moving there. Diagonal moves are only
+    // allowed if both of the straight cells next to them are walkable, so that paths don't cut
+    // corners of obstacles.
+    fn neighbours(&self, (x, y): NavCell) -> impl Iterator<Item = (NavCell, u32)> + '_ {
+        let offsets = [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+        IntoIterator::into_iter(offsets).filter_map(move |(dx, dy)| {
+            let cell = (
+                usize::try_from(x as i64 + dx).ok()?,
+                usize::try_from(y as i64 + dy).ok()?,
+            );
+
+            if !self.is_cell_walkable(cell) {
+                return None;
+            }
+
+            if dx != 0 && dy != 0 {
+                let corners_walkable = self.is_cell_walkable((cell.0, y)) && self.is_cell_walkable((x, cell.1));
+                corners_walkable.then(|| (cell, DIAGONAL_COST))
+            } else {
+                Some((cell, STRAIGHT_COST))
+            }
+        })
+    }
+
+    // Finds the shortest path between two points using A*. Returns the waypoints to walk through,
+    // ending at `to`, or `None` if the target isn't walkable or there's no way there. The start
+    // doesn't need to be walkable, since characters may stand a bit closer to obstacles than the
+    // grid allows.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.cell_at(from)?;
+        let goal = self.cell_at(to)?;
+
+        if !self.is_cell_walkable(goal) {
+            return None;
+        }
+
+        // Octile distance, i.e. the cost of the path if there were no obstacles.
+        let heuristic = |(x, y): NavCell| {
+            let dx = (x as i64 - goal.0 as i64).unsigned_abs() as u32;
+            let dy = (y as i64 - goal.1 as i64).unsigned_abs() as u32;
+            STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+        };
+
+        let mut open = BinaryHeap::new();
+        let mut costs = HashMap::new();
+        let mut came_from = HashMap::new();
+
+        open.push(Reverse((heuristic(start), start)));
+        costs.insert(start, 0);
+
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                let mut cells = vec![cell];
+
+                while let Some(previous) = came_from.get(cells.last().unwrap()) {
+                    cells.push(*previous);
+                }
+
+                cells.reverse();
+                return Some(self.waypoints(&cells, to));
+            }
+
+            let cost = costs[&cell];
+
+            for (neighbour, step_cost) in self.neighbours(cell) {
+                let neighbour_cost = cost + step_cost;
+
+                if costs.get(&neighbour).map_or(true, |x| neighbour_cost < *x) {
+                    costs.insert(neighbour, neighbour_cost);
+                    came_from.insert(neighbour, cell);
+                    open.push(Reverse((neighbour_cost + heuristic(neighbour), neighbour)));
+                }
+            }
+        }
+
+        None
+    }
+
+    // Turns a path of cells into waypoints, keeping only the cells where the path changes
+    // direction. The start cell is skipped since the character is already there, and the last
+    // waypoint is the exact target rather than the center of its cell.
+    fn waypoints(&self, cells: &[NavCell], to: Vec2) -> Vec<Vec2> {
+        let mut result = vec![];
+
+        for i in 1..cells.len().saturating_sub(1) {
+            let direction = |a: NavCell, b: NavCell| (b.0 as i64 - a.0 as i64, b.1 as i64 - a.1 as i64);
+
+            if direction(cells[i - 1], cells[i]) != direction(cells[i], cells[i + 1]) {
+                result.push(self.cell_center(cells[i]));
+            }
+        }
+
+        result.push(to);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wall(point: Vec2) -> bool {
+        point.x > 5.0 && point.x < 6.0 && point.y < 8.0
+    }
+
+    // 10 by 10 grid of unit cells with a wall across the middle, open at the bottom.
+    fn walled_grid() -> NavGrid {
+        let mut grid = NavGrid::new(vec2(0.0, 0.0), vec2(10.0, 10.0), 1.0);
+        grid.add_blocker(vec2(0.0, 0.0), vec2(10.0, 10.0), wall);
+        grid
+    }
+
+    #[test]
+    fn walks_around_obstacles() {
+        let grid = walled_grid();
+        let path = grid.find_path(vec2(1.5, 1.5), vec2(8.5, 1.5)).unwrap();
+
+        assert_eq!(path.last(), Some(&vec2(8.5, 1.5)));
+
+        // Every leg of the path stays on walkable ground.
+        let mut previous = vec2(1.5, 1.5);
+        for waypoint in &path {
+            for i in 0..=20 {
+                assert!(grid.is_walkable(previous.lerp(*waypoint, i as f32 / 20.0)));
+            }
+            previous = *waypoint;
+        }
+
+        // Goes through the gap below the wall.
+        assert!(path.iter().any(|waypoint| waypoint.y > 8.0));
+    }
+
+    #[test]
+    fn straight_paths_have_no_detours() {
+        let grid = walled_grid();
+        let path = grid.find_path(vec2(0.5, 9.5), vec2(9.5, 9.5)).unwrap();
+
+        assert_eq!(path, vec![vec2(9.5, 9.5)]);
+    }
+
+    #[test]
+    fn unreachable_targets() {
+        let mut grid = walled_grid();
+
+        assert!(grid.find_path(vec2(1.5, 1.5), vec2(5.5, 1.5)).is_none());
+        assert!(grid.find_path(vec2(1.5, 1.5), vec2(20.0, 1.5)).is_none());
+
+        // Close the gap.
+        let gap = |point: Vec2| point.x > 5.0 && point.x < 6.0 && point.y > 8.0;
+        grid.add_blocker(vec2(5.0, 8.0), vec2(6.0, 10.0), gap);
+        assert!(grid.find_path(vec2(1.5, 1.5), vec2(8.5, 1.5)).is_none());
+
+        // Obstacles can be removed independently, even where they overlap others.
+        grid.add_blocker(vec2(0.0, 0.0), vec2(10.0, 10.0), wall);
+        grid.remove_blocker(vec2(5.0, 8.0), vec2(6.0, 10.0), gap);
+        assert!(grid.find_path(vec2(1.5, 1.5), vec2(8.5, 1.5)).is_some());
+        grid.remove_blocker(vec2(0.0, 0.0), vec2(10.0, 10.0), wall);
+        assert!(!grid.is_walkable(vec2(5.5, 1.5)));
+
+        grid.clear();
+        assert_eq!(
+            grid.find_path(vec2(1.5, 1.5), vec2(8.5, 1.5)),
+            Some(vec![vec2(8.5, 1.5)])
+        );
+    }
+}
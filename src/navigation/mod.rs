@@ -0,0 +1,5 @@
+mod grid;
+mod walkability;
+
+pub use grid::*;
+pub use walkability::*;
@@ -0,0 +1,398 @@
+use super::*;
+use hecs::Entity;
+use macroquad::prelude::*;
+use std::collections::HashMap;
+
+// Size of a walkability grid cell, in world units. Small enough for characters to squeeze between
+// furniture, large enough for the grid to be cheap to update.
+pub const NAV_GRID_CELL_SIZE: f32 = 5.0;
+
+// How far `nearest_walkable_point` looks around the given point, in cells.
+const NEAREST_SEARCH_RADIUS: usize = 64;
+
+// Where characters can stand: inside the walkable area, and clear of every obstacle. Obstacles are
+// the collider footprints of static objects, and are added and removed along with the objects, so
+// only the cells around them need to be updated.
+pub struct WalkabilityMap {
+    grid: NavGrid,
+    // World-space outline characters must stay within, e.g. the room bounds.
+    area: Vec<Vec2>,
+    // Half extent of the characters on the ground plane. Obstacles are inflated by it, so that a
+    // walkable cell is a spot a character fits in.
+    agent_half_extent: Vec2,
+    // World-space footprints (convex outlines) of each object's colliders.
+    obstacles: HashMap<Entity, Vec<Vec<Vec2>>>,
+}
+
+impl WalkabilityMap {
+    pub fn new(area: Vec<Vec2>, agent_half_extent: Vec2) -> Self {
+        let (min, max) = bounds(&area);
+        let mut result = Self {
+            grid: NavGrid::new(min, max, NAV_GRID_CELL_SIZE),
+            area,
+            agent_half_extent,
+            obstacles: HashMap::new(),
+        };
+
+        result.rebuild();
+        result
+    }
+
+    pub fn agent_half_extent(&self) -> Vec2 {
+        self.agent_half_extent
+    }
+
+    // Changes the outline characters must stay within. Like changing the character size, this
+    // rebuilds the whole map.
+    pub fn set_area(&mut self, area: Vec<Vec2>) {
+        let (min, max) = bounds(&area);
+        self.grid = NavGrid::new(min, max, NAV_GRID_CELL_SIZE);
+        self.area = area;
+        self.rebuild();
+    }
+
+    // Changes the character size. Unlike adding or removing obstacles, this rebuilds the whole map.
+    pub fn set_agent_half_extent(&mut self, agent_half_extent: Vec2) {
+        if self.agent_half_extent != agent_half_extent {
+            self.agent_half_extent = agent_half_extent;
+            self.rebuild();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.grid.clear();
+
+        // Characters need to fit within the area as a whole, since they can't walk through its
+        // walls.
+        let (min, max) = bounds(&self.area);
+        let area = &self.area;
+        let half_extent = self.agent_half_extent;
+        self.grid
+            .add_blocker(min, max, |point| !polygon_contains_rect(area, point, half_extent));
+
+        let footprints = self.obstacles.values().flatten().cloned().collect::<Vec<_>>();
+        for footprint in &footprints {
+            self.update_footprint(footprint, true);
+        }
+    }
+
+    fn update_footprint(&mut self, footprint: &[Vec2], add: bool) {
+        let half_extent = self.agent_half_extent;
+        let (min, max) = bounds(footprint);
+        let covers = |point: Vec2| polygon_overlaps_rect(footprint, point, half_extent);
+
+        if add {
+            self.grid.add_blocker(min - half_extent, max + half_extent, covers);
+        } else {
+            self.grid.remove_blocker(min - half_extent, max + half_extent, covers);
+        }
+    }
+
+    // Adds the collider footprints of an object. Replaces the object's previous footprints, if any.
+    pub fn add_obstacle(&mut self, object: Entity, footprints: Vec<Vec<Vec2>>) {
+        self.remove_obstacle(object);
+
+        for footprint in &footprints {
+            self.update_footprint(footprint, true);
+        }
+
+        self.obstacles.insert(object, footprints);
+    }
+
+    pub fn remove_obstacle(&mut self, object: Entity) {
+        if let Some(footprints) = self.obstacles.remove(&object) {
+            for footprint in &footprints {
+                self.update_footprint(footprint, false);
+            }
+        }
+    }
+
+    pub fn move_obstacle(&mut self, object: Entity, offset: Vec2) {
+        if let Some(footprints) = self.obstacles.get(&object) {
+            let footprints = footprints
+                .iter()
+                .map(|footprint| footprint.iter().map(|x| *x + offset).collect())
+                .collect();
+
+            self.add_obstacle(object, footprints);
+        }
+    }
+
+    pub fn is_walkable(&self, point: Vec2) -> bool {
+        self.grid.is_walkable(point)
+    }
+
+    // Closest walkable point to the given one, or `None` if there's none nearby. Walkable points are
+    // returned as is, otherwise the result is the center of the closest walkable cell.
+    pub fn nearest_walkable_point(&self, point: Vec2) -> Option<Vec2> {
+        if self.is_walkable(point) {
+            return Some(point);
+        }
+
+        let grid = &self.grid;
+        let cell = ((point - grid.origin) / grid.cell_size).floor();
+        let cell = (cell.x as i64, cell.y as i64);
+
+        let mut result: Option<Vec2> = None;
+
+        for radius in 1..=NEAREST_SEARCH_RADIUS as i64 {
+            // Cells of a ring are at least this far from the point, wherever in its cell the point
+            // is. Cells further out may still be closer than the best one so far though, e.g. one
+            // straight ahead two rings further than a diagonal one.
+            let min_distance = (radius as f32 - 0.5) * grid.cell_size;
+
+            if result.map_or(false, |x| x.distance(point) <= min_distance) {
+                break;
+            }
+
+            for (x, y) in ring(cell, radius) {
+                if x < 0 || y < 0 || !grid.is_cell_walkable((x as usize, y as usize)) {
+                    continue;
+                }
+
+                let center = grid.cell_center((x as usize, y as usize));
+                if result.map_or(true, |x| center.distance(point) < x.distance(point)) {
+                    result = Some(center);
+                }
+            }
+        }
+
+        result
+    }
+
+    // Shortest path between two points, as waypoints ending at `to`. See `NavGrid::find_path`.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        self.grid.find_path(from, to)
+    }
+
+    pub fn grid(&self) -> &NavGrid {
+        &self.grid
+    }
+}
+
+// Cells at the given Chebyshev distance from the center cell.
+fn ring((x, y): (i64, i64), radius: i64) -> impl Iterator<Item = (i64, i64)> {
+    (-radius..=radius).flat_map(move |i| {
+        let sides = [(x + i, y - radius), (x + i, y + radius)];
+        let corners_excluded = (i > -radius && i < radius).then(|| [(x - radius, y + i), (x + radius, y + i)]);
+
+        IntoIterator::into_iter(sides).chain(corners_excluded.into_iter().flatten())
+    })
+}
+
+fn bounds(points: &[Vec2]) -> (Vec2, Vec2) {
+    let mut min = vec2(f32::MAX, f32::MAX);
+    let mut max = vec2(f32::MIN, f32::MIN);
+
+    for point in points {
+        min = min.min(*point);
+        max = max.max(*point);
+    }
+
+    (min, max)
+}
+
+// Whether all the corners of an axis-aligned rect are within a polygon. Exact for convex polygons,
+// and close enough for the occasional concave corner of a room.
+fn polygon_contains_rect(points: &[Vec2], center: Vec2, half_extent: Vec2) -> bool {
+    [vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0)]
+        .iter()
+        .all(|corner| polygon_contains(points, center + *corner * half_extent))
+}
+
+// Even-odd rule point in polygon test, which works for any simple polygon.
+fn polygon_contains(points: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+// Separating axis test between a convex polygon and an axis-aligned rect. Shapes that only touch
+// don't overlap.
+fn polygon_overlaps_rect(points: &[Vec2], center: Vec2, half_extent: Vec2) -> bool {
+    if points.len() < 3 {
+        return false;
+    }
+
+    let edge_normals = (0..points.len()).map(|i| (points[(i + 1) % points.len()] - points[i]).perp());
+
+    for axis in IntoIterator::into_iter([Vec2::X, Vec2::Y]).chain(edge_normals) {
+        let (min, max) = points.iter().fold((f32::MAX, f32::MIN), |(min, max), point| {
+            let projection = point.dot(axis);
+            (min.min(projection), max.max(projection))
+        });
+
+        let rect_center = center.dot(axis);
+        let rect_radius = half_extent.x * axis.x.abs() + half_extent.y * axis.y.abs();
+
+        if max <= rect_center - rect_radius || min >= rect_center + rect_radius {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(min: Vec2, size: f32) -> Vec<Vec2> {
+        vec![
+            min,
+            min + vec2(size, 0.0),
+            min + vec2(size, size),
+            min + vec2(0.0, size),
+        ]
+    }
+
+    fn map() -> WalkabilityMap {
+        WalkabilityMap::new(square(vec2(0.0, 0.0), 200.0), vec2(10.0, 10.0))
+    }
+
+    #[test]
+    fn obstacles_are_inflated() {
+        let mut map = map();
+        let mut world = hecs::World::new();
+        let object = world.spawn(());
+
+        map.add_obstacle(object, vec![square(vec2(50.0, 50.0), 20.0)]);
+
+        assert!(!map.is_walkable(vec2(60.0, 60.0)));
+        // Within the character half extent from the obstacle.
+        assert!(!map.is_walkable(vec2(42.0, 60.0)));
+        assert!(map.is_walkable(vec2(37.0, 60.0)));
+
+        // Outside of the area, or too close to its edge for the character to fit.
+        assert!(!map.is_walkable(vec2(-10.0, 60.0)));
+        assert!(!map.is_walkable(vec2(210.0, 60.0)));
+        assert!(!map.is_walkable(vec2(5.0, 60.0)));
+        assert!(map.is_walkable(vec2(15.0, 60.0)));
+
+        // Growing characters need more room.
+        map.set_agent_half_extent(vec2(20.0, 20.0));
+        assert!(!map.is_walkable(vec2(37.0, 60.0)));
+    }
+
+    #[test]
+    fn obstacles_update_incrementally() {
+        let mut map = map();
+        let mut world = hecs::World::new();
+        let first = world.spawn(());
+        let second = world.spawn(());
+
+        // Overlapping obstacles.
+        map.add_obstacle(first, vec![square(vec2(50.0, 50.0), 20.0)]);
+        map.add_obstacle(second, vec![square(vec2(60.0, 60.0), 20.0)]);
+
+        map.remove_obstacle(first);
+        assert!(map.is_walkable(vec2(45.0, 45.0)));
+        assert!(!map.is_walkable(vec2(65.0, 65.0)));
+
+        map.move_obstacle(second, vec2(100.0, 0.0));
+        assert!(map.is_walkable(vec2(65.0, 65.0)));
+        assert!(!map.is_walkable(vec2(165.0, 65.0)));
+    }
+
+    #[test]
+    fn concave_area() {
+        let mut map = map();
+
+        // L-shaped room, without the top right quarter.
+        map.set_area(vec![
+            vec2(0.0, 0.0),
+            vec2(100.0, 0.0),
+            vec2(100.0, 100.0),
+            vec2(200.0, 100.0),
+            vec2(200.0, 200.0),
+            vec2(0.0, 200.0),
+        ]);
+
+        assert!(map.is_walkable(vec2(50.0, 50.0)));
+        assert!(map.is_walkable(vec2(150.0, 150.0)));
+        assert!(!map.is_walkable(vec2(150.0, 50.0)));
+
+        let path = map.find_path(vec2(50.0, 50.0), vec2(150.0, 150.0)).unwrap();
+        assert!(path.iter().all(|x| map.is_walkable(*x)));
+    }
+
+    #[test]
+    fn nearest_walkable_point() {
+        let mut map = map();
+        let mut world = hecs::World::new();
+        let object = world.spawn(());
+
+        map.add_obstacle(object, vec![square(vec2(50.0, 50.0), 20.0)]);
+
+        assert_eq!(map.nearest_walkable_point(vec2(100.0, 100.0)), Some(vec2(100.0, 100.0)));
+
+        // Closest to the left side of the inflated obstacle.
+        let point = map.nearest_walkable_point(vec2(45.0, 60.0)).unwrap();
+        assert!(map.is_walkable(point));
+        assert!(point.distance(vec2(40.0, 60.0)) < NAV_GRID_CELL_SIZE);
+
+        let path = map.find_path(vec2(20.0, 60.0), vec2(100.0, 60.0)).unwrap();
+        assert_eq!(path.last(), Some(&vec2(100.0, 60.0)));
+    }
+
+    #[test]
+    fn nearest_walkable_point_is_nearest() {
+        let mut map = map();
+        let mut world = hecs::World::new();
+        let object = world.spawn(());
+
+        // A cross with a short top arm, so that from around its center, a walkable cell straight
+        // up is closer than the diagonal ones, even though it's further away in cells.
+        let rect = |min: Vec2, max: Vec2| vec![min, vec2(max.x, min.y), max, vec2(min.x, max.y)];
+        map.add_obstacle(
+            object,
+            vec![
+                rect(vec2(30.0, 70.0), vec2(170.0, 130.0)),
+                rect(vec2(70.0, 45.0), vec2(130.0, 170.0)),
+            ],
+        );
+
+        let walkable_cells = map
+            .grid()
+            .cells()
+            .filter(|(_, walkable)| *walkable)
+            .map(|(center, _)| center)
+            .collect::<Vec<_>>();
+
+        for y in 0..80 {
+            for x in 0..80 {
+                let point = vec2(x as f32, y as f32) * 2.5 + 0.1;
+
+                if map.is_walkable(point) {
+                    continue;
+                }
+
+                let nearest = walkable_cells
+                    .iter()
+                    .map(|x| x.distance(point))
+                    .fold(f32::MAX, f32::min);
+                let result = map.nearest_walkable_point(point).unwrap();
+
+                assert!(map.is_walkable(result));
+                assert!(
+                    (result.distance(point) - nearest).abs() < 1e-3,
+                    "nearest walkable point to {point} is {nearest} away, found one {} away",
+                    result.distance(point)
+                );
+            }
+        }
+    }
+}
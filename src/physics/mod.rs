@@ -17,8 +17,9 @@ pub struct PhysicsWorld {
 // would need to be customized in real world, maybe not. Don't have time to dig deep into
 // the docs and figure out which parameters would be ideal.
 //
-// Right now it features only two things: physics body creation and simulation step. Also some
-// helper methods to help with 2D scene integration.
+// Right now it features only a few things: physics body creation (cuboids, cylinders, convex
+// hulls, room boundaries and sensors), removal and teleportation, and simulation step.
+// Also some helper methods to help with 2D scene integration.
 impl PhysicsWorld {
     pub fn new() -> Self {
         Self {
@@ -59,20 +60,182 @@ impl PhysicsWorld {
     pub fn create_body_cuboid(&mut self, body_type: RigidBodyType, origin: Vec3, half_extent: Vec3) -> RigidBodyHandle {
         println!("adding cuboid collider: origin={origin:?} half_extent={half_extent:?}");
 
+        let collider = ColliderBuilder::cuboid(half_extent.x, half_extent.y, half_extent.z).build();
+
+        self.create_body(body_type, origin, collider)
+    }
+
+    // Creates a body with a sensor cuboid collider, which detects other colliders overlapping it
+    // instead of blocking them. See `bodies_intersect`.
+    pub fn create_body_sensor_cuboid(
+        &mut self,
+        body_type: RigidBodyType,
+        origin: Vec3,
+        half_extent: Vec3,
+    ) -> RigidBodyHandle {
+        println!("adding cuboid sensor: origin={origin:?} half_extent={half_extent:?}");
+
+        let collider = ColliderBuilder::cuboid(half_extent.x, half_extent.y, half_extent.z)
+            .sensor(true)
+            .build();
+
+        self.create_body(body_type, origin, collider)
+    }
+
+    // Creates a body with an upright cylinder collider, i.e. with its axis along Z.
+    pub fn create_body_cylinder(
+        &mut self,
+        body_type: RigidBodyType,
+        origin: Vec3,
+        radius: f32,
+        half_height: f32,
+    ) -> RigidBodyHandle {
+        println!("adding cylinder collider: origin={origin:?} radius={radius} half_height={half_height}");
+
+        // Rapier cylinders are aligned with Y axis, so rotate it up.
+        let collider = ColliderBuilder::cylinder(half_height, radius)
+            .rotation(vector![std::f32::consts::FRAC_PI_2, 0.0, 0.0])
+            .build();
+
+        self.create_body(body_type, origin, collider)
+    }
+
+    // Creates a body with a convex hull collider of the given points, relative to the body origin.
+    // Returns `None` if the points don't form a hull, e.g. if they're all on the same plane.
+    pub fn create_body_convex_hull(
+        &mut self,
+        body_type: RigidBodyType,
+        origin: Vec3,
+        points: &[Vec3],
+    ) -> Option<RigidBodyHandle> {
+        println!("adding convex hull collider: origin={origin:?} points={}", points.len());
+
+        let points = points.iter().map(|x| point![x.x, x.y, x.z]).collect::<Vec<_>>();
+        let collider = ColliderBuilder::convex_hull(&points)?.build();
+
+        Some(self.create_body(body_type, origin, collider))
+    }
+
+    // Creates a static body with a wall along each edge of the given polygon, which keeps whatever is
+    // inside from getting out. Walls are built outwards from the edges, so that none of the polygon
+    // is taken up by them.
+    pub fn create_body_boundary(&mut self, points: &[Vec2], thickness: f32, height: f32) -> RigidBodyHandle {
+        println!("adding boundary colliders: points={}", points.len());
+
+        let edges = (0..points.len()).map(|i| (points[i], points[(i + 1) % points.len()]));
+
+        // Right-hand edge normals point outwards for counter-clockwise polygons, so flip them for
+        // clockwise ones.
+        let signed_area = edges.clone().map(|(from, to)| from.perp_dot(to)).sum::<f32>();
+        let winding = if signed_area < 0.0 { -1.0 } else { 1.0 };
+
+        // Corners where the polygon turns the same way it winds, as opposed to concave ones.
+        let is_convex = |i: usize| {
+            let previous = points[i] - points[(i + points.len() - 1) % points.len()];
+            let next = points[(i + 1) % points.len()] - points[i];
+            previous.perp_dot(next) * winding > 0.0
+        };
+
+        let rigid_body = RigidBodyBuilder::new(RigidBodyType::Static).build();
+        let body_handle = self.rigid_body_set.insert(rigid_body);
+
+        for (i, (from, to)) in edges.enumerate() {
+            let edge = to - from;
+            let direction = edge.normalize_or_zero();
+            let normal = vec2(direction.y, -direction.x) * winding;
+
+            // Walls stick out past convex corners, so that there are no gaps between them. At
+            // concave corners they would stick into the polygon instead, and the walls overlap
+            // there anyway.
+            let from = if is_convex(i) {
+                from - direction * thickness
+            } else {
+                from
+            };
+            let to = if is_convex((i + 1) % points.len()) {
+                to + direction * thickness
+            } else {
+                to
+            };
+            let center = (from + to) * 0.5 + normal * thickness * 0.5;
+
+            let collider = ColliderBuilder::cuboid(from.distance(to) * 0.5, thickness * 0.5, height * 0.5)
+                .translation(vector![center.x, center.y, height * 0.5])
+                .rotation(vector![0.0, 0.0, edge.y.atan2(edge.x)])
+                .build();
+
+            self.collider_set
+                .insert_with_parent(collider, body_handle, &mut self.rigid_body_set);
+        }
+
+        body_handle
+    }
+
+    fn create_body(&mut self, body_type: RigidBodyType, origin: Vec3, collider: Collider) -> RigidBodyHandle {
         let rigid_body = RigidBodyBuilder::new(body_type)
             .translation(vector![origin.x, origin.y, origin.z])
             .lock_rotations()
             .build();
         let body_handle = self.rigid_body_set.insert(rigid_body);
 
-        let collider = ColliderBuilder::cuboid(half_extent.x, half_extent.y, half_extent.z).build();
-
         self.collider_set
             .insert_with_parent(collider, body_handle, &mut self.rigid_body_set);
 
         body_handle
     }
 
+    // Removes the body along with all of its colliders.
+    pub fn remove_body(&mut self, handle: RigidBodyHandle) {
+        self.rigid_body_set.remove(
+            handle,
+            &mut self.island_manager,
+            &mut self.collider_set,
+            &mut self.joint_set,
+        );
+    }
+
+    // Center, half-extent and rotation around the Z axis of every collider, for debug visualization.
+    // Cuboids (e.g. room boundary walls) are exact, other shapes are approximated with their
+    // bounding box.
+    pub fn collider_boxes(&self) -> impl Iterator<Item = (Vec3, Vec3, f32)> + '_ {
+        self.collider_set.iter().map(|(_, collider)| {
+            if let Some(cuboid) = collider.shape().as_cuboid() {
+                let position = collider.position();
+                let center = position.translation.vector;
+                let half_extent = cuboid.half_extents;
+                let (_, _, rotation) = position.rotation.euler_angles();
+
+                return (
+                    vec3(center.x, center.y, center.z),
+                    vec3(half_extent.x, half_extent.y, half_extent.z),
+                    rotation,
+                );
+            }
+
+            let aabb = collider.compute_aabb();
+            let center = aabb.center();
+            let half_extent = aabb.half_extents();
+
+            (
+                vec3(center.x, center.y, center.z),
+                vec3(half_extent.x, half_extent.y, half_extent.z),
+                0.0,
+            )
+        })
+    }
+
+    // Whether any colliders of the two bodies overlap as of the last simulation step. Only tracked
+    // if one of them is a sensor.
+    pub fn bodies_intersect(&self, a: RigidBodyHandle, b: RigidBodyHandle) -> bool {
+        let b_colliders = self.rigid_body_set[b].colliders();
+
+        self.rigid_body_set[a].colliders().iter().any(|a| {
+            b_colliders
+                .iter()
+                .any(|b| self.narrow_phase.intersection_pair(*a, *b) == Some(true))
+        })
+    }
+
     // Some helper methods below for easier 2D scene integration.
 
     pub fn set_body_linear_velocity(&mut self, handle: RigidBodyHandle, vel: Vec3) {
@@ -80,6 +243,12 @@ impl PhysicsWorld {
         body.set_linvel(vector![vel.x, vel.y, vel.z], true);
     }
 
+    // Teleports the body to the given position.
+    pub fn set_body_translation(&mut self, handle: RigidBodyHandle, translation: Vec3) {
+        let body = &mut self.rigid_body_set[handle];
+        body.set_translation(vector![translation.x, translation.y, translation.z], true);
+    }
+
     #[inline]
     pub fn set_body_linear_velocity_2d(&mut self, handle: RigidBodyHandle, vel: Vec2) {
         self.set_body_linear_velocity(handle, vec3(vel.x, vel.y, 0.0));
@@ -96,4 +265,18 @@ impl PhysicsWorld {
         let translation = self.get_body_translation(handle);
         vec2(translation.x, translation.y)
     }
+
+    // Velocity the body actually moved at in the last simulation step, e.g. slower than it was set
+    // to when sliding along a wall.
+    pub fn get_body_linear_velocity(&self, handle: RigidBodyHandle) -> Vec3 {
+        let body = &self.rigid_body_set[handle];
+        let vel = body.linvel();
+        vec3(vel.x, vel.y, vel.z)
+    }
+
+    #[inline]
+    pub fn get_body_linear_velocity_2d(&self, handle: RigidBodyHandle) -> Vec2 {
+        let vel = self.get_body_linear_velocity(handle);
+        vec2(vel.x, vel.y)
+    }
 }
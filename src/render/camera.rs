@@ -0,0 +1,267 @@
+use hecs::Entity;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const CAMERA_MIN_ZOOM: f32 = 0.5;
+pub const CAMERA_MAX_ZOOM: f32 = 4.0;
+// Zoom factor of a single mouse wheel step.
+pub const CAMERA_ZOOM_STEP: f32 = 1.1;
+
+// How big the scene appears in the window. Applied every frame, so the scene adapts to window
+// resizes and DPI changes.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ViewScalePolicy {
+    // The whole map fits the window.
+    FitMap,
+    // Same as fitting the map, but rounded down to a whole number of window pixels per texture
+    // pixel, so that sprites stay crisp.
+    PixelPerfect,
+    // A screen-space unit is a logical pixel, so bigger windows show more of the scene.
+    #[default]
+    FixedWorldUnits,
+}
+
+// Scene view. Draw calls are laid out in screen space as if the map was drawn at the top left of
+// the window, and the camera maps that onto the actual window. So anything that takes mouse
+// positions needs to convert them with `window_to_screen` first.
+pub struct SceneCamera {
+    // Entity the camera follows, e.g. the player character.
+    pub target: Option<Entity>,
+    // Whether the camera follows the target. Cleared when panning manually.
+    pub following: bool,
+    // Screen-space point at the center of the window.
+    pub position: Vec2,
+    pub scale_policy: ViewScalePolicy,
+    // Zoom on top of the scale policy, see `view_zoom`.
+    pub zoom: f32,
+    // Screen-space size of a texture pixel, i.e. the scene scale. Used by the pixel-perfect policy.
+    pub texel_size: f32,
+    // Half size of the area around the window center the target can move in without the camera
+    // following it, in window pixels.
+    pub dead_zone: Vec2,
+    // How fast the camera catches up with the target, roughly the fraction of the distance
+    // covered per second.
+    pub follow_rate: f32,
+    // Screen-space area the view is kept within, e.g. the background image.
+    pub bounds: Option<Rect>,
+    // Window size in pixels, as of the last update.
+    viewport: Vec2,
+    // Window pixels per logical pixel.
+    dpi_scale: f32,
+}
+
+impl Default for SceneCamera {
+    fn default() -> Self {
+        Self {
+            target: None,
+            following: true,
+            position: Vec2::ZERO,
+            scale_policy: ViewScalePolicy::default(),
+            zoom: 1.0,
+            texel_size: 1.0,
+            dead_zone: vec2(80.0, 60.0),
+            follow_rate: 5.0,
+            bounds: None,
+            viewport: vec2(1.0, 1.0),
+            dpi_scale: 1.0,
+        }
+    }
+}
+
+impl SceneCamera {
+    pub fn viewport(&self) -> Vec2 {
+        self.viewport
+    }
+
+    pub fn set_viewport(&mut self, viewport: Vec2, dpi_scale: f32) {
+        self.viewport = viewport.max(vec2(1.0, 1.0));
+        self.dpi_scale = dpi_scale.max(f32::EPSILON);
+    }
+
+    // Window pixels per screen-space unit, given the scale policy, the window size and the zoom.
+    pub fn view_zoom(&self) -> f32 {
+        // Zoom that fits the map into the window. Without a map, there's nothing to fit, so fall
+        // back to fixed units.
+        let fit_zoom = self
+            .bounds
+            .map(|bounds| (self.viewport.x / bounds.w).min(self.viewport.y / bounds.h))
+            .unwrap_or(self.dpi_scale);
+
+        match self.scale_policy {
+            ViewScalePolicy::FitMap => fit_zoom * self.zoom,
+            ViewScalePolicy::PixelPerfect => {
+                let pixels_per_texel = (fit_zoom * self.texel_size * self.zoom).floor().max(1.0);
+                pixels_per_texel / self.texel_size
+            }
+            ViewScalePolicy::FixedWorldUnits => self.dpi_scale * self.zoom,
+        }
+    }
+
+    // Moves the camera towards the target, given its screen-space position. The camera only moves
+    // once the target leaves the dead zone, and then eases in instead of snapping to it.
+    pub fn follow(&mut self, target_position: Vec2, dt: f32) {
+        let dead_zone = self.dead_zone / self.view_zoom();
+        let offset = target_position - self.position;
+        let desired = self.position + offset - offset.clamp(-dead_zone, dead_zone);
+
+        // Exponential smoothing, so that the camera behaves the same at any frame rate.
+        let t = 1.0 - (-self.follow_rate * dt).exp();
+        self.position = self.position.lerp(desired, t);
+    }
+
+    pub fn zoom_by(&mut self, steps: f32) {
+        self.zoom = (self.zoom * CAMERA_ZOOM_STEP.powf(steps)).clamp(CAMERA_MIN_ZOOM, CAMERA_MAX_ZOOM);
+    }
+
+    // Moves the view along with the mouse, given the distance it was dragged in window pixels.
+    pub fn pan(&mut self, window_delta: Vec2) {
+        self.position -= window_delta / self.view_zoom();
+        self.following = false;
+    }
+
+    // Keeps the view within the bounds. Bounds smaller than the view are centered instead.
+    pub fn clamp_to_bounds(&mut self) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let half_view = self.viewport * 0.5 / self.view_zoom();
+        let clamp = |position: f32, min: f32, size: f32, half_view: f32| {
+            if size <= half_view * 2.0 {
+                min + size * 0.5
+            } else {
+                position.clamp(min + half_view, min + size - half_view)
+            }
+        };
+
+        self.position = vec2(
+            clamp(self.position.x, bounds.x, bounds.w, half_view.x),
+            clamp(self.position.y, bounds.y, bounds.h, half_view.y),
+        );
+    }
+
+    #[must_use]
+    pub fn window_to_screen(&self, window_position: Vec2) -> Vec2 {
+        self.position + (window_position - self.viewport * 0.5) / self.view_zoom()
+    }
+
+    #[must_use]
+    pub fn screen_to_window(&self, screen_position: Vec2) -> Vec2 {
+        (screen_position - self.position) * self.view_zoom() + self.viewport * 0.5
+    }
+
+    pub fn camera_2d(&self) -> Camera2D {
+        let zoom = self.view_zoom();
+
+        // Texels only land on whole pixels if the view does too.
+        let target = match self.scale_policy {
+            ViewScalePolicy::PixelPerfect => (self.position * zoom).round() / zoom,
+            _ => self.position,
+        };
+
+        Camera2D {
+            target,
+            // Window y goes down, same as screen space.
+            zoom: vec2(2.0 / self.viewport.x, -2.0 / self.viewport.y) * zoom,
+            ..Default::default()
+        }
+    }
+
+    // Makes subsequent draw calls go through the camera, until `set_default_camera` is called.
+    pub fn apply(&self) {
+        set_camera(&self.camera_2d());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> SceneCamera {
+        let mut camera = SceneCamera {
+            zoom: 2.0,
+            dead_zone: vec2(100.0, 100.0),
+            ..Default::default()
+        };
+        camera.set_viewport(vec2(800.0, 600.0), 1.0);
+        camera
+    }
+
+    #[test]
+    fn window_to_screen_round_trip() {
+        let camera = SceneCamera {
+            position: vec2(100.0, 50.0),
+            ..camera()
+        };
+
+        assert_eq!(camera.window_to_screen(vec2(400.0, 300.0)), vec2(100.0, 50.0));
+        assert_eq!(camera.window_to_screen(vec2(0.0, 0.0)), vec2(-100.0, -100.0));
+        assert_eq!(camera.screen_to_window(vec2(-100.0, -100.0)), vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn follows_outside_of_dead_zone() {
+        let mut camera = camera();
+
+        // The dead zone is 50 screen-space units at this zoom.
+        camera.follow(vec2(40.0, -40.0), 1.0);
+        assert_eq!(camera.position, Vec2::ZERO);
+
+        // Eventually catches up, keeping the target at the edge of the dead zone.
+        for _ in 0..100 {
+            camera.follow(vec2(150.0, 0.0), 0.1);
+        }
+        assert!(camera.position.distance(vec2(100.0, 0.0)) < 0.01);
+    }
+
+    #[test]
+    fn stays_within_bounds() {
+        let mut camera = camera();
+        camera.bounds = Some(Rect::new(0.0, 0.0, 1000.0, 200.0));
+
+        // Half of the view is 200 by 150 units, the bounds are too short to fit it vertically.
+        camera.position = vec2(-500.0, -500.0);
+        camera.clamp_to_bounds();
+        assert_eq!(camera.position, vec2(200.0, 100.0));
+
+        camera.position = vec2(2000.0, 0.0);
+        camera.clamp_to_bounds();
+        assert_eq!(camera.position, vec2(800.0, 100.0));
+
+        camera.zoom_by(-100.0);
+        assert_eq!(camera.zoom, CAMERA_MIN_ZOOM);
+    }
+
+    #[test]
+    fn scale_policies() {
+        let mut camera = SceneCamera {
+            bounds: Some(Rect::new(0.0, 0.0, 1000.0, 500.0)),
+            texel_size: 0.25,
+            ..Default::default()
+        };
+        camera.set_viewport(vec2(1600.0, 1200.0), 2.0);
+
+        camera.scale_policy = ViewScalePolicy::FixedWorldUnits;
+        assert_eq!(camera.view_zoom(), 2.0);
+
+        // Limited by the width.
+        camera.scale_policy = ViewScalePolicy::FitMap;
+        assert_eq!(camera.view_zoom(), 1.6);
+
+        // 0.4 window pixels per texel, rounded up to a single one.
+        camera.scale_policy = ViewScalePolicy::PixelPerfect;
+        assert_eq!(camera.view_zoom(), 4.0);
+
+        // Zooming in goes in whole pixels per texel.
+        camera.zoom = 6.0;
+        assert_eq!(camera.view_zoom(), 8.0);
+
+        // Resizing the window rescales the view.
+        camera.scale_policy = ViewScalePolicy::FitMap;
+        camera.zoom = 1.0;
+        camera.set_viewport(vec2(800.0, 200.0), 1.0);
+        assert_eq!(camera.view_zoom(), 0.4);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{math::*, navigation::*, physics::*, scene::*};
+use macroquad::prelude::*;
+
+const COLLIDER_COLOR: Color = Color::new(0.0, 1.0, 0.0, 0.8);
+const SPRITE_BOUNDS_COLOR: Color = Color::new(1.0, 0.0, 1.0, 0.8);
+const FRONT_POINT_COLOR: Color = Color::new(1.0, 1.0, 0.0, 1.0);
+const UNWALKABLE_COLOR: Color = Color::new(1.0, 0.0, 0.0, 0.3);
+
+pub struct DebugDrawSettings {
+    pub colliders: bool,
+    pub shadows: bool,
+    pub sprite_bounds: bool,
+    pub front_points: bool,
+    pub walkability: bool,
+}
+
+impl Default for DebugDrawSettings {
+    fn default() -> Self {
+        Self {
+            colliders: false,
+            shadows: true,
+            sprite_bounds: false,
+            front_points: false,
+            walkability: false,
+        }
+    }
+}
+
+// Projects a point in 3D world space to the screen. Height goes straight up the screen, using the
+// same units as the isometric plane.
+#[inline]
+fn world_to_screen_3d(point: Vec3) -> Vec2 {
+    world_to_screen(point.truncate()) - vec2(0.0, point.z)
+}
+
+// Draws a cuboid rotated around the Z axis by `rotation` radians.
+pub fn draw_world_space_cuboid_lines(center: Vec3, half_extent: Vec3, rotation: f32, color: Color) {
+    let (sin, cos) = rotation.sin_cos();
+    let rotate = |x: f32, y: f32| center.truncate() + vec2(x * cos - y * sin, x * sin + y * cos);
+
+    let corners = |z: f32| {
+        [
+            rotate(-half_extent.x, -half_extent.y),
+            rotate(half_extent.x, -half_extent.y),
+            rotate(half_extent.x, half_extent.y),
+            rotate(-half_extent.x, half_extent.y),
+        ]
+        .map(|corner| world_to_screen_3d(corner.extend(z)))
+    };
+
+    let bottom = corners(center.z - half_extent.z);
+    let top = corners(center.z + half_extent.z);
+
+    for i in 0..4 {
+        let j = (i + 1) % 4;
+
+        draw_line(bottom[i].x, bottom[i].y, bottom[j].x, bottom[j].y, 1.0, color);
+        draw_line(top[i].x, top[i].y, top[j].x, top[j].y, 1.0, color);
+        draw_line(bottom[i].x, bottom[i].y, top[i].x, top[i].y, 1.0, color);
+    }
+}
+
+pub fn render_physics_colliders(physics: &PhysicsWorld) {
+    for (center, half_extent, rotation) in physics.collider_boxes() {
+        draw_world_space_cuboid_lines(center, half_extent, rotation, COLLIDER_COLOR);
+    }
+}
+
+pub fn render_sprite_bounds(world: &mut hecs::World) {
+    for (_, dc) in world.query_mut::<&SpriteDrawCallComponent>() {
+        let dc = &dc.draw_call;
+        draw_rectangle_lines(
+            dc.origin.x,
+            dc.origin.y,
+            dc.extent.x,
+            dc.extent.y,
+            1.0,
+            SPRITE_BOUNDS_COLOR,
+        );
+    }
+}
+
+pub fn render_front_points(world: &mut hecs::World) {
+    for (_, dc) in world.query_mut::<&SpriteDrawCallComponent>() {
+        let front_point = world_to_screen(dc.draw_call.front_point);
+        draw_circle(front_point.x, front_point.y, 3.0, FRONT_POINT_COLOR);
+    }
+}
+
+// Shades the cells characters can't stand in.
+pub fn render_walkability(map: &WalkabilityMap) {
+    let grid = map.grid();
+    let half_size = grid.cell_size * 0.5;
+    let corners = [
+        vec2(-half_size, -half_size),
+        vec2(half_size, -half_size),
+        vec2(half_size, half_size),
+        vec2(-half_size, half_size),
+    ];
+
+    for (center, walkable) in grid.cells() {
+        if walkable {
+            continue;
+        }
+
+        let [a, b, c, d] = corners.map(|corner| world_to_screen(center + corner));
+        draw_triangle(a, b, c, UNWALKABLE_COLOR);
+        draw_triangle(a, c, d, UNWALKABLE_COLOR);
+    }
+}
@@ -1,30 +1,16 @@
+mod camera;
+mod debug;
 mod resource;
+mod shadow;
 
-use crate::{math::*, scene::*};
+use crate::scene::*;
+pub use camera::*;
+pub use debug::*;
 use macroquad::prelude::*;
 pub use resource::*;
+pub use shadow::*;
 use std::cmp::Ordering;
 
-pub struct WorldSpaceRectDrawCall {
-    pub origin: Vec2,
-    pub extent: Vec2,
-    pub color: Color,
-}
-
-impl WorldSpaceRectDrawCall {
-    pub fn draw(&self) {
-        let v1 = world_to_screen(self.origin);
-        let v2 = world_to_screen(self.origin + vec2(self.extent.x, 0.0));
-        let v3 = world_to_screen(self.origin + vec2(self.extent.x, self.extent.y));
-        let v4 = world_to_screen(self.origin + vec2(0.0, self.extent.y));
-
-        // Drawing as separate triangles is obviously suboptimal, but in the interests of time we
-        // won't be combining quads into a single mesh.
-        draw_triangle(v1, v2, v3, self.color);
-        draw_triangle(v1, v3, v4, self.color);
-    }
-}
-
 #[derive(Default)]
 pub struct SpriteDrawCall {
     pub origin: Vec2,
@@ -33,13 +19,43 @@ pub struct SpriteDrawCall {
     pub flip_x: bool,
     pub flip_y: bool,
     pub order: f32,
+    // World-space front point of the sprite, used for depth sorting.
+    pub front_point: Vec2,
+    // Part of the texture to draw, e.g. an animation frame in a sprite sheet. The whole texture is
+    // drawn if not set.
+    pub source: Option<Rect>,
 }
 
 impl SpriteDrawCall {
+    // Size of the drawn part of the texture, in texture pixels.
+    pub fn source_size(&self) -> Option<Vec2> {
+        match (&self.source, &self.texture) {
+            (Some(source), _) => Some(source.size()),
+            (None, Some(texture)) => Some(get_texture_size(texture)),
+            (None, None) => None,
+        }
+    }
+
+    // Changes the drawn part of the texture, keeping the sprite centered where it was. This way
+    // frames of different sizes don't need the whole draw call to be recomputed.
+    pub fn set_source(&mut self, source: Option<Rect>, scale: f32) {
+        self.source = source;
+
+        // Sprites without a texture have no extent anyway.
+        if self.texture.is_none() {
+            return;
+        }
+
+        let extent = self.source_size().unwrap_or(Vec2::ZERO) * scale;
+        self.origin += (self.extent - extent) * 0.5;
+        self.extent = extent;
+    }
+
     pub fn draw(&self) {
         if let Some(texture) = &self.texture {
             let params = DrawTextureParams {
                 dest_size: Some(self.extent),
+                source: self.source,
                 flip_x: self.flip_x,
                 flip_y: self.flip_y,
                 ..Default::default()
@@ -50,16 +66,6 @@ impl SpriteDrawCall {
     }
 }
 
-pub fn render_background_geometry(world: &mut hecs::World) {
-    // This renders shadows without sorting, since they'll always be behind other objects.
-    for (_, shadow) in world.query_mut::<&SceneObjectShadowComponent>() {
-        // Dispatch draw calls.
-        for draw_call in &shadow.draw_calls {
-            draw_call.draw();
-        }
-    }
-}
-
 pub fn render_foreground_geometry(world: &mut hecs::World) {
     // Ideally, we'd not recreate the vector here to avoid allocations in render loop, but who cares.
     let mut draw_calls = vec![];
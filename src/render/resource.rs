@@ -16,6 +16,12 @@ impl TextureWrapper {
             inner: Texture2D::from_file_with_format(&buf, Some(image::ImageFormat::Png)),
         }
     }
+
+    pub(self) fn from_rgba8(width: u16, height: u16, data: &[u8]) -> Self {
+        Self {
+            inner: Texture2D::from_rgba8(width, height, data),
+        }
+    }
 }
 
 impl Drop for TextureWrapper {
@@ -35,3 +41,8 @@ impl Deref for TextureWrapper {
 pub fn create_texture_resource(buf: bytes::Bytes) -> TextureResource {
     Arc::new(TextureWrapper::new(buf))
 }
+
+// Creates a texture out of already decoded image data, see `ImageLoader`.
+pub fn create_texture_resource_from_rgba8(width: u16, height: u16, data: &[u8]) -> TextureResource {
+    Arc::new(TextureWrapper::from_rgba8(width, height, data))
+}
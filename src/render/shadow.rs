@@ -0,0 +1,332 @@
+use super::*;
+use crate::{asset::ELLIPSE_SEGMENT_COUNT, math::*, scene::*};
+use std::convert::TryFrom;
+
+// Size of a single shadow map texel, in screen pixels. Shadows are mostly blurry, so there's no
+// need for full resolution, and the texture is filtered when drawn anyway.
+pub const SHADOW_MAP_TEXEL_SIZE: f32 = 2.0;
+
+// Shadow of a static object: a convex polygon on the isometric plane with a blurred edge.
+pub struct ShadowDrawCall {
+    pub points: Vec<Vec2>,
+    // Width of the blurred edge, in world units.
+    pub softness: f32,
+    pub opacity: f32,
+}
+
+impl ShadowDrawCall {
+    // Signed distance from the point to the polygon outline, negative inside the polygon.
+    fn signed_distance(&self, point: Vec2) -> f32 {
+        let mut distance = f32::MAX;
+        let mut sign = 0.0;
+        let mut inside = true;
+
+        for i in 0..self.points.len() {
+            let a = self.points[i];
+            let b = self.points[(i + 1) % self.points.len()];
+            let edge = b - a;
+
+            let t = ((point - a).dot(edge) / edge.length_squared()).clamp(0.0, 1.0);
+            distance = distance.min(point.distance(a + edge * t));
+
+            // Polygons are convex, so the point is inside if it's on the same side of every edge,
+            // whichever the winding order is.
+            let cross = edge.perp_dot(point - a);
+            if cross.abs() > f32::EPSILON {
+                if sign != 0.0 && cross.signum() != sign {
+                    inside = false;
+                }
+
+                sign = cross.signum();
+            }
+        }
+
+        if inside {
+            -distance
+        } else {
+            distance
+        }
+    }
+
+    // Opacity of the shadow at the given world-space point.
+    fn opacity_at(&self, point: Vec2, min_softness: f32) -> f32 {
+        let softness = self.softness.max(min_softness);
+        let t = (0.5 - self.signed_distance(point) / softness).clamp(0.0, 1.0);
+
+        // Smoothstep, so that the blurred edge doesn't end abruptly.
+        t * t * (3.0 - 2.0 * t) * self.opacity
+    }
+
+    // Screen-space bounds of everything the shadow covers, including the blurred edge.
+    fn screen_bounds(&self) -> Option<(Vec2, Vec2)> {
+        if self.points.len() < 3 {
+            return None;
+        }
+
+        let mut min = vec2(f32::MAX, f32::MAX);
+        let mut max = vec2(f32::MIN, f32::MIN);
+
+        for point in &self.points {
+            min = min.min(*point);
+            max = max.max(*point);
+        }
+
+        let margin = vec2(self.softness, self.softness);
+        let corners = [
+            min - margin,
+            vec2(max.x + margin.x, min.y - margin.y),
+            max + margin,
+            vec2(min.x - margin.x, max.y + margin.y),
+        ];
+
+        let mut screen_min = vec2(f32::MAX, f32::MAX);
+        let mut screen_max = vec2(f32::MIN, f32::MIN);
+
+        for corner in corners {
+            let corner = world_to_screen(corner);
+            screen_min = screen_min.min(corner);
+            screen_max = screen_max.max(corner);
+        }
+
+        Some((screen_min, screen_max))
+    }
+}
+
+// Shadow opacity rasterized in screen space, one value per texel.
+pub struct ShadowImage {
+    // Screen position of the top left corner.
+    pub origin: Vec2,
+    pub width: usize,
+    pub height: usize,
+    pub texel_size: f32,
+    pub opacity: Vec<f32>,
+}
+
+impl ShadowImage {
+    pub fn opacity_at(&self, screen_position: Vec2) -> f32 {
+        let texel = (screen_position - self.origin) / self.texel_size;
+
+        if texel.x < 0.0 || texel.y < 0.0 || texel.x as usize >= self.width || texel.y as usize >= self.height {
+            return 0.0;
+        }
+
+        self.opacity[texel.y as usize * self.width + texel.x as usize]
+    }
+
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.opacity
+            .iter()
+            .flat_map(|opacity| [0, 0, 0, (opacity * 255.0).round() as u8])
+            .collect()
+    }
+}
+
+// Rasterizes shadows into a single image. Where shadows overlap, the darkest one wins instead of
+// them being blended together, so that overlapping shadows don't darken into blotches. Returns
+// `None` if there's nothing to draw.
+pub fn rasterize_shadows<'a>(
+    draw_calls: impl IntoIterator<Item = &'a ShadowDrawCall>,
+    texel_size: f32,
+) -> Option<ShadowImage> {
+    let draw_calls = draw_calls
+        .into_iter()
+        .filter_map(|draw_call| Some((draw_call, draw_call.screen_bounds()?)))
+        .collect::<Vec<_>>();
+
+    let mut min = vec2(f32::MAX, f32::MAX);
+    let mut max = vec2(f32::MIN, f32::MIN);
+
+    for (_, (draw_call_min, draw_call_max)) in &draw_calls {
+        min = min.min(*draw_call_min);
+        max = max.max(*draw_call_max);
+    }
+
+    if draw_calls.is_empty() {
+        return None;
+    }
+
+    let origin = (min / texel_size).floor() * texel_size;
+    let width = ((max.x - origin.x) / texel_size).ceil() as usize;
+    let height = ((max.y - origin.y) / texel_size).ceil() as usize;
+    let mut opacity = vec![0.0; width * height];
+
+    // A texel covers about this much of the world, so edges narrower than that would alias.
+    let min_softness = screen_to_world(vec2(texel_size, 0.0)).length();
+
+    for (draw_call, (draw_call_min, draw_call_max)) in draw_calls {
+        let from = ((draw_call_min - origin) / texel_size).floor();
+        let to = ((draw_call_max - origin) / texel_size).ceil();
+
+        for y in from.y as usize..(to.y as usize).min(height) {
+            for x in from.x as usize..(to.x as usize).min(width) {
+                let screen_position = origin + (vec2(x as f32, y as f32) + 0.5) * texel_size;
+                let texel = &mut opacity[y * width + x];
+
+                *texel = texel.max(draw_call.opacity_at(screen_to_world(screen_position), min_softness));
+            }
+        }
+    }
+
+    Some(ShadowImage {
+        origin,
+        width,
+        height,
+        texel_size,
+        opacity,
+    })
+}
+
+// Shadow of a moving object, e.g. a character: a blurred ellipse on the isometric plane. Unlike the
+// static shadows, these change every frame, so instead of being baked they're drawn directly as a
+// mesh with a faded edge.
+pub struct DynamicShadowDrawCall {
+    pub center: Vec2,
+    pub radii: Vec2,
+    // Width of the blurred edge, in world units.
+    pub softness: f32,
+    pub opacity: f32,
+}
+
+impl DynamicShadowDrawCall {
+    pub fn draw(&self) {
+        let color = Color::new(0.0, 0.0, 0.0, self.opacity);
+        let edge_color = Color::new(0.0, 0.0, 0.0, 0.0);
+
+        // The edge fades out from the inner ring to the outer one, centered on the ellipse outline.
+        let inner_radii = (self.radii - self.softness * 0.5).max(Vec2::ZERO);
+        let outer_radii = self.radii + self.softness * 0.5;
+
+        let vertex = |radii: Vec2, direction: Vec2, color: Color| {
+            let position = world_to_screen(self.center + direction * radii);
+            Vertex::new(position.x, position.y, 0.0, 0.0, 0.0, color)
+        };
+
+        let mut vertices = vec![vertex(Vec2::ZERO, Vec2::ZERO, color)];
+        let mut indices = vec![];
+
+        for i in 0..ELLIPSE_SEGMENT_COUNT {
+            let angle = i as f32 / ELLIPSE_SEGMENT_COUNT as f32 * std::f32::consts::TAU;
+            let direction = vec2(angle.cos(), angle.sin());
+
+            vertices.push(vertex(inner_radii, direction, color));
+            vertices.push(vertex(outer_radii, direction, edge_color));
+
+            // Vertex 0 is the center, followed by inner and outer ring vertices of each segment.
+            let inner = (1 + i * 2) as u16;
+            let outer = inner + 1;
+            let next_inner = (1 + (i + 1) % ELLIPSE_SEGMENT_COUNT * 2) as u16;
+            let next_outer = next_inner + 1;
+
+            indices.extend_from_slice(&[0, inner, next_inner]);
+            indices.extend_from_slice(&[inner, outer, next_outer]);
+            indices.extend_from_slice(&[inner, next_outer, next_inner]);
+        }
+
+        draw_mesh(&Mesh {
+            vertices,
+            indices,
+            texture: None,
+        });
+    }
+}
+
+pub fn render_dynamic_shadows(world: &mut hecs::World) {
+    for (_, shadow) in world.query_mut::<&DynamicShadowComponent>() {
+        shadow.draw_call.draw();
+    }
+}
+
+// Shadows of all the static objects, baked into a single texture. Rebaked on the next draw after
+// being invalidated, i.e. whenever objects are spawned, moved or despawned.
+#[derive(Default)]
+pub struct ShadowMap {
+    dirty: bool,
+    draw_call: Option<SpriteDrawCall>,
+}
+
+impl ShadowMap {
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn draw(&mut self, world: &mut hecs::World) {
+        if self.dirty {
+            self.dirty = false;
+            self.draw_call = None;
+
+            let mut shadows = world.query::<&SceneObjectShadowComponent>();
+            let draw_calls = shadows.iter().flat_map(|(_, shadow)| shadow.draw_calls.iter());
+
+            if let Some(image) = rasterize_shadows(draw_calls, SHADOW_MAP_TEXEL_SIZE) {
+                let texture = match (u16::try_from(image.width), u16::try_from(image.height)) {
+                    (Ok(width), Ok(height)) => create_texture_resource_from_rgba8(width, height, &image.to_rgba8()),
+                    _ => {
+                        println!("shadow map is too large: {}x{}", image.width, image.height);
+                        return;
+                    }
+                };
+
+                self.draw_call = Some(SpriteDrawCall {
+                    origin: image.origin,
+                    extent: vec2(image.width as f32, image.height as f32) * image.texel_size,
+                    texture: Some(texture),
+                    ..Default::default()
+                });
+            }
+        }
+
+        if let Some(draw_call) = &self.draw_call {
+            draw_call.draw();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(origin: Vec2, size: f32, softness: f32, opacity: f32) -> ShadowDrawCall {
+        ShadowDrawCall {
+            points: vec![
+                origin,
+                origin + vec2(size, 0.0),
+                origin + vec2(size, size),
+                origin + vec2(0.0, size),
+            ],
+            softness,
+            opacity,
+        }
+    }
+
+    #[test]
+    fn overlapping_shadows_dont_darken() {
+        let shadows = [
+            square(vec2(0.0, 0.0), 100.0, 0.0, 0.2),
+            square(vec2(50.0, 50.0), 100.0, 0.0, 0.2),
+            square(vec2(60.0, 60.0), 20.0, 0.0, 0.4),
+        ];
+        let image = rasterize_shadows(&shadows, 1.0).unwrap();
+
+        // Inside both of the big squares, but outside of the small one.
+        assert!((image.opacity_at(world_to_screen(vec2(90.0, 90.0))) - 0.2).abs() < 1e-4);
+        // Inside all three, the darkest one wins.
+        assert!((image.opacity_at(world_to_screen(vec2(70.0, 70.0))) - 0.4).abs() < 1e-4);
+        // Outside of everything.
+        assert_eq!(image.opacity_at(world_to_screen(vec2(200.0, 0.0))), 0.0);
+    }
+
+    #[test]
+    fn soft_edges_fade_out() {
+        let shadows = [square(vec2(0.0, 0.0), 100.0, 40.0, 0.5)];
+        let image = rasterize_shadows(&shadows, 1.0).unwrap();
+        let opacity_at = |x: f32| image.opacity_at(world_to_screen(vec2(x, 50.0)));
+
+        assert!((opacity_at(50.0) - 0.5).abs() < 1e-4);
+        assert!((opacity_at(100.0) - 0.25).abs() < 0.05);
+        assert_eq!(opacity_at(125.0), 0.0);
+
+        // Fades out monotonically across the edge.
+        let samples = (0..=10).map(|i| opacity_at(75.0 + i as f32 * 5.0)).collect::<Vec<_>>();
+        assert!(samples.windows(2).all(|x| x[0] >= x[1]));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::navigation::WalkabilityMap;
+use hecs::{Bundle, Entity};
+use macroquad::{prelude::*, rand::gen_range};
+
+// Characters are considered to have arrived at their destination this close to it, in world units.
+const ARRIVAL_RADIUS: f32 = 1.0;
+
+// Wandering characters stand still for a random time in this range between walks, in seconds.
+const WANDER_PAUSE: (f32, f32) = (1.0, 4.0);
+
+// Random destinations a wandering character tries before pausing again, if none of them can be
+// walked to.
+const WANDER_ATTEMPTS: usize = 8;
+
+// Characters give up on a destination they haven't reached within this time, e.g. because another
+// character is in the way, in seconds.
+const GIVE_UP_TIME: f32 = 5.0;
+
+// Following characters look for a new path this often while walking, since the target keeps
+// moving, in seconds.
+const FOLLOW_REPATH_INTERVAL: f32 = 0.5;
+
+// What a non-player character does on its own. Positions are those of the character's physics body,
+// in world space.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AiBehaviour {
+    // Stands still.
+    Idle,
+    // Walks to random places within the radius around where it was spawned, pausing in between.
+    Wander { radius: f32 },
+    // Walks through the waypoints in order, over and over again. Waypoints that can't be reached
+    // are skipped.
+    Patrol { waypoints: Vec<Vec2> },
+    // Walks after another character, keeping the given distance to it.
+    Follow { target: Entity, distance: f32 },
+}
+
+// Drives a character by sending it along paths around obstacles, the same way clicking sends the
+// player, see `Scene::update_ai`.
+#[derive(Bundle)]
+pub struct AiComponent {
+    pub behaviour: AiBehaviour,
+    // Where the character was spawned, which wandering characters stay around.
+    pub home: Vec2,
+    // Where the current path leads, for wandering and patrolling characters.
+    destination: Option<Vec2>,
+    // Index of the patrol waypoint the character walks to.
+    next_waypoint: usize,
+    // Time left to pause, to reach the destination, or until looking for a new path, in seconds.
+    timer: f32,
+}
+
+impl AiComponent {
+    pub fn new(behaviour: AiBehaviour, home: Vec2) -> Self {
+        Self {
+            behaviour,
+            home,
+            destination: None,
+            next_waypoint: 0,
+            timer: 0.0,
+        }
+    }
+
+    // Entity the character follows, if any.
+    pub fn follow_target(&self) -> Option<Entity> {
+        match self.behaviour {
+            AiBehaviour::Follow { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+
+    // Advances the behaviour by a step. `walking` tells whether the character still has a path to
+    // walk along, and `target_position` is the position of the followed entity, if it still exists.
+    // Returns a new path to replace the current one with, if the character should change course. An
+    // empty path stops the character.
+    pub fn tick(
+        &mut self,
+        position: Vec2,
+        walking: bool,
+        target_position: Option<Vec2>,
+        walkability: &WalkabilityMap,
+        dt: f32,
+    ) -> Option<Vec<Vec2>> {
+        self.timer -= dt;
+
+        match &self.behaviour {
+            AiBehaviour::Idle => None,
+            AiBehaviour::Wander { radius } => {
+                let radius = *radius;
+
+                if walking {
+                    if self.timer > 0.0 {
+                        return None;
+                    }
+
+                    // Taking too long, so something must be in the way.
+                    self.destination = None;
+                    self.timer = gen_range(WANDER_PAUSE.0, WANDER_PAUSE.1);
+                    return Some(vec![]);
+                }
+
+                if self.destination.take().is_some() {
+                    self.timer = gen_range(WANDER_PAUSE.0, WANDER_PAUSE.1);
+                    return None;
+                }
+
+                if self.timer > 0.0 {
+                    return None;
+                }
+
+                for _ in 0..WANDER_ATTEMPTS {
+                    // Pick a point uniformly within the circle around home.
+                    let angle = gen_range(0.0, std::f32::consts::TAU);
+                    let distance = radius * gen_range(0.0f32, 1.0).sqrt();
+                    let destination = self.home + vec2(angle.cos(), angle.sin()) * distance;
+
+                    if !walkability.is_walkable(destination) {
+                        continue;
+                    }
+
+                    if let Some(path) = walkability.find_path(position, destination) {
+                        self.destination = Some(destination);
+                        self.timer = GIVE_UP_TIME;
+                        return Some(path);
+                    }
+                }
+
+                self.timer = gen_range(WANDER_PAUSE.0, WANDER_PAUSE.1);
+                None
+            }
+            AiBehaviour::Patrol { waypoints } => {
+                if waypoints.is_empty() {
+                    return None;
+                }
+
+                if walking {
+                    if self.timer > 0.0 {
+                        return None;
+                    }
+
+                    // Taking too long, so move on to the next waypoint rather than getting stuck.
+                    return Some(vec![]);
+                }
+
+                if self.destination.take().is_some() {
+                    self.next_waypoint = (self.next_waypoint + 1) % waypoints.len();
+                }
+
+                // Waypoints covered by obstacles are walked up to as close as possible.
+                let waypoint = waypoints[self.next_waypoint % waypoints.len()];
+                let path = walkability
+                    .nearest_walkable_point(waypoint)
+                    .and_then(|goal| walkability.find_path(position, goal));
+
+                match path {
+                    Some(path) => {
+                        self.destination = Some(waypoint);
+                        self.timer = GIVE_UP_TIME;
+                        Some(path)
+                    }
+                    None => {
+                        self.next_waypoint = (self.next_waypoint + 1) % waypoints.len();
+                        None
+                    }
+                }
+            }
+            AiBehaviour::Follow { distance, .. } => {
+                let target_position = match target_position {
+                    Some(target_position) => target_position,
+                    None => return walking.then(Vec::new),
+                };
+
+                let offset = target_position - position;
+                if offset.length() <= *distance + ARRIVAL_RADIUS {
+                    return walking.then(Vec::new);
+                }
+
+                if walking && self.timer > 0.0 {
+                    return None;
+                }
+
+                self.timer = FOLLOW_REPATH_INTERVAL;
+
+                // Stop short of the target, so that the character doesn't bump into it. Standing
+                // still is all there's left to do if there's no way to the target.
+                let goal = target_position - offset.normalize() * *distance;
+                let path = walkability
+                    .nearest_walkable_point(goal)
+                    .and_then(|goal| walkability.find_path(position, goal));
+
+                Some(path.unwrap_or_default())
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
+use super::description::*;
 use crate::{asset::*, render::*};
-use hecs::Bundle;
+use hecs::{Bundle, Entity};
 use macroquad::prelude::*;
 use rapier3d::prelude::RigidBodyHandle;
+use std::collections::{HashMap, VecDeque};
 
 #[derive(Bundle)]
 pub struct SpriteDrawCallComponent {
@@ -14,6 +16,42 @@ pub struct RootTransformComponent {
     pub scale: f32,
 }
 
+// Marks the root entity of a placed static object and remembers where it came from, so that the
+// scene can be saved back to a file.
+#[derive(Bundle)]
+pub struct SceneObjectComponent {
+    pub asset_path: String,
+    pub orientation: AssetOrientation,
+    // Image entities the object consists of.
+    pub parts: Vec<Entity>,
+}
+
+// Links an image entity of a static object back to the object's root entity.
+#[derive(Bundle)]
+pub struct SceneObjectPartComponent {
+    pub object: Entity,
+}
+
+#[derive(Bundle)]
+pub struct CharacterSourceComponent {
+    pub description: CharacterDescription,
+}
+
+// Remembers the description a non-player character was spawned from, so that it can be saved.
+#[derive(Bundle)]
+pub struct NpcSourceComponent {
+    pub description: NpcDescription,
+}
+
+// Doorway to another scene. Its physics body is a sensor, which detects the player walking in.
+#[derive(Bundle)]
+pub struct DoorwayComponent {
+    pub description: DoorwayDescription,
+    // Whether the player was in the doorway as of the last update, so that the doorway only leads
+    // somewhere when the player walks into it, not while standing in it.
+    pub player_inside: bool,
+}
+
 #[derive(Bundle)]
 pub struct AssetTransformComponent {
     pub transform: AssetTransform,
@@ -27,12 +65,138 @@ pub struct PhysicsBodyCollectionComponent {
 #[derive(Bundle)]
 pub struct CharacterVelocityComponent {
     pub velocity: Vec2,
+    // Speed the character moves at given full input, from the character definition.
+    pub movement_speed: f32,
 }
 
+// Waypoints a character walks through, e.g. after the player clicked somewhere. Empty unless the
+// character is walking along a path, see `Scene::follow_paths`.
 #[derive(Bundle)]
-pub struct CharacterTransformComponent {
+pub struct CharacterPathComponent {
+    // Half extent of the character's collider, so that paths keep it clear of obstacles.
+    pub half_extent: Vec3,
+    pub waypoints: VecDeque<Vec2>,
+}
+
+// Character sprite for a single facing direction.
+#[derive(Clone)]
+pub struct CharacterSprite {
+    pub texture: Option<TextureResource>,
     pub offset: Vec2,
     pub front_point: Vec2,
+    pub clips: HashMap<String, AnimationClipData>,
+}
+
+// Per-direction character sprites, and the direction the character currently faces.
+#[derive(Bundle)]
+pub struct CharacterSpritesComponent {
+    pub sprites: HashMap<AssetOrientation, CharacterSprite>,
+    // Direction the character appears to face on screen, which may differ from the one it moves in
+    // if there's no sprite for the latter.
+    pub facing: AssetOrientation,
+    // Sprite used for the current facing, which is mirrored if `flip_x` is set.
+    pub sprite: AssetOrientation,
+    pub flip_x: bool,
+}
+
+impl CharacterSpritesComponent {
+    pub fn new(sprites: HashMap<AssetOrientation, CharacterSprite>, facing: AssetOrientation) -> Self {
+        let mut result = Self {
+            sprites,
+            facing,
+            sprite: facing,
+            flip_x: false,
+        };
+
+        result.face(facing);
+        result
+    }
+
+    // Turns the character to the given direction, picking the closest sprite available. Sprites can
+    // be mirrored, so e.g. a character with only a `S` sprite can still face `E`. If two sprites are
+    // equally close, the character keeps its current facing, so that it doesn't flicker between
+    // them.
+    pub fn face(&mut self, direction: AssetOrientation) {
+        let candidates = self
+            .sprites
+            .keys()
+            .flat_map(|sprite| [(*sprite, *sprite, false), (sprite.mirrored(), *sprite, true)]);
+
+        let best = candidates.min_by_key(|(facing, sprite, flip_x)| {
+            (
+                facing.steps_to(direction),
+                *facing != self.facing,
+                *flip_x,
+                AssetOrientation::ALL.iter().position(|x| x == sprite),
+            )
+        });
+
+        if let Some((facing, sprite, flip_x)) = best {
+            self.facing = facing;
+            self.sprite = sprite;
+            self.flip_x = flip_x;
+        }
+    }
+
+    pub fn current(&self) -> Option<&CharacterSprite> {
+        self.sprites.get(&self.sprite)
+    }
+}
+
+// Sprite sheet animation: plays one of the named clips, advanced every frame by `Scene::simulate`.
+#[derive(Bundle)]
+pub struct AnimationComponent {
+    pub clips: HashMap<String, AnimationClipData>,
+    pub current: String,
+    // Playback position of the current clip, in seconds.
+    pub time: f32,
+    // Playback speed multiplier, e.g. to match the walk cycle to the movement speed.
+    pub speed: f32,
+}
+
+impl AnimationComponent {
+    pub fn new(clips: HashMap<String, AnimationClipData>, clip: &str) -> Self {
+        Self {
+            clips,
+            current: clip.to_owned(),
+            time: 0.0,
+            speed: 1.0,
+        }
+    }
+
+    // Switches to the given clip, starting it from the beginning. Does nothing if the clip is
+    // already playing, so this can be called every frame.
+    pub fn play(&mut self, clip: &str) {
+        if self.current != clip {
+            self.current = clip.to_owned();
+            self.time = 0.0;
+        }
+    }
+
+    // Replaces the clips while keeping the playback position, e.g. when a character turns around
+    // mid-stride and switches to the clips of another direction.
+    pub fn set_clips(&mut self, clips: HashMap<String, AnimationClipData>) {
+        self.clips = clips;
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        self.time += dt * self.speed;
+    }
+
+    // Frame of the current clip at the current playback position, or `None` if there's no such
+    // clip.
+    pub fn current_frame(&self) -> Option<FrameRect> {
+        let clip = self.clips.get(&self.current)?;
+        let frame = (self.time * clip.frames_per_second).max(0.0) as usize;
+
+        let index = if clip.looping {
+            frame % clip.frames.len()
+        } else {
+            frame.min(clip.frames.len() - 1)
+        };
+
+        clip.frames.get(index).copied()
+    }
 }
 
 #[derive(Bundle)]
@@ -42,8 +206,10 @@ pub struct PhysicsBodyComponent {
 
 #[derive(Bundle)]
 pub struct SceneObjectShadowComponent {
-    pub primitives: Vec<AssetPrimitive>,
-    pub draw_calls: Vec<WorldSpaceRectDrawCall>,
+    pub primitives: Vec<AssetShadowPrimitive>,
+    // Opacity of the shadows, set per asset.
+    pub opacity: f32,
+    pub draw_calls: Vec<ShadowDrawCall>,
 }
 
 impl SceneObjectShadowComponent {
@@ -52,12 +218,123 @@ impl SceneObjectShadowComponent {
         self.draw_calls.clear();
 
         for primitive in &self.primitives {
-            let extent: Vec2 = primitive.shape.scale.vec2() * scale;
-            let offset: Vec2 = primitive.shape.position.vec2() * scale;
-            let origin = object_position + offset;
-            let color = Color::new(0.0, 0.0, 0.0, 0.15);
+            let points = primitive
+                .shape
+                .outline()
+                .into_iter()
+                .map(|x| object_position + x * scale)
+                .collect();
+
+            self.draw_calls.push(ShadowDrawCall {
+                points,
+                softness: primitive.softness * scale,
+                opacity: self.opacity,
+            });
+        }
+    }
+}
+
+// Shadow footprint of a moving object, following its physics body. See `update_dynamic_objects`.
+#[derive(Bundle)]
+pub struct DynamicShadowComponent {
+    pub draw_call: DynamicShadowDrawCall,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprites(directions: &[AssetOrientation]) -> HashMap<AssetOrientation, CharacterSprite> {
+        let sprite = CharacterSprite {
+            texture: None,
+            offset: vec2(0.0, 0.0),
+            front_point: vec2(0.0, 0.0),
+            clips: HashMap::new(),
+        };
 
-            self.draw_calls.push(WorldSpaceRectDrawCall { origin, extent, color });
+        directions.iter().map(|x| (*x, sprite.clone())).collect()
+    }
+
+    #[test]
+    fn character_faces_closest_sprite() {
+        use AssetOrientation::*;
+
+        // A single sprite can be mirrored to face the other way.
+        let mut comp = CharacterSpritesComponent::new(sprites(&[South]), South);
+        assert_eq!((comp.facing, comp.sprite, comp.flip_x), (South, South, false));
+
+        comp.face(East);
+        assert_eq!((comp.facing, comp.sprite, comp.flip_x), (East, South, true));
+
+        // `S` and `E` are equally close to `SE`, so keep facing `E`.
+        comp.face(SouthEast);
+        assert_eq!((comp.facing, comp.sprite, comp.flip_x), (East, South, true));
+
+        comp.face(West);
+        assert_eq!((comp.facing, comp.sprite, comp.flip_x), (South, South, false));
+
+        // Prefer actual sprites over mirrored ones.
+        let mut comp = CharacterSpritesComponent::new(sprites(&[South, East, North, West]), South);
+        comp.face(East);
+        assert_eq!((comp.facing, comp.sprite, comp.flip_x), (East, East, false));
+
+        // Diagonals fall back to the closest cardinal direction.
+        comp.face(NorthEast);
+        assert_eq!((comp.facing, comp.sprite, comp.flip_x), (East, East, false));
+
+        let mut comp = CharacterSpritesComponent::new(sprites(&[South, SouthEast]), South);
+        comp.face(SouthEast);
+        assert_eq!((comp.facing, comp.sprite, comp.flip_x), (SouthEast, SouthEast, false));
+    }
+
+    fn clip(frame_count: usize, looping: bool) -> AnimationClipData {
+        AnimationClipData {
+            frames: (0..frame_count)
+                .map(|i| FrameRect {
+                    x: i as f32 * 10.0,
+                    y: 0.0,
+                    width: 10.0,
+                    height: 20.0,
+                })
+                .collect(),
+            frames_per_second: 10.0,
+            looping,
         }
     }
+
+    #[test]
+    fn animation_playback() {
+        let clips = HashMap::from([
+            (IDLE_CLIP.to_owned(), clip(2, true)),
+            (WALK_CLIP.to_owned(), clip(4, false)),
+        ]);
+        let mut animation = AnimationComponent::new(clips, IDLE_CLIP);
+        let frame_x = |animation: &AnimationComponent| animation.current_frame().map(|frame| frame.x);
+
+        assert_eq!(frame_x(&animation), Some(0.0));
+
+        // Looping clips wrap around.
+        animation.advance(0.25);
+        assert_eq!(frame_x(&animation), Some(0.0));
+        animation.advance(0.1);
+        assert_eq!(frame_x(&animation), Some(10.0));
+
+        // Switching clips restarts playback, but playing the current one again doesn't.
+        animation.play(WALK_CLIP);
+        animation.advance(0.15);
+        animation.play(WALK_CLIP);
+        assert_eq!(frame_x(&animation), Some(10.0));
+
+        // Playback speed scales time.
+        animation.speed = 2.0;
+        animation.advance(0.05);
+        assert_eq!(frame_x(&animation), Some(20.0));
+
+        // Clips that don't loop stop at the last frame.
+        animation.advance(10.0);
+        assert_eq!(frame_x(&animation), Some(30.0));
+
+        animation.play("missing");
+        assert_eq!(frame_x(&animation), None);
+    }
 }
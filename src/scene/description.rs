@@ -0,0 +1,121 @@
+use crate::{asset::*, render::ViewScalePolicy};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs::File, io::BufWriter};
+
+// Scene files are authored by hand, so keep the format close to the asset metadata: camel case
+// keys, `Point` objects instead of tuples and asset paths relative to the working directory.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneDescription {
+    // Screen-space size of a texture pixel, i.e. how big the art is relative to the world.
+    pub scale: f32,
+    // How the scene is scaled to the window, see `ViewScalePolicy`.
+    #[serde(default)]
+    pub view_scale: ViewScalePolicy,
+    pub background: Option<String>,
+    // World-space polygon characters can't leave, e.g. the floor of the room. Defaults to the
+    // outline of the background map.
+    #[serde(default)]
+    pub bounds: Option<Vec<Point>>,
+    pub assets: Vec<String>,
+    #[serde(default)]
+    pub objects: Vec<SceneObjectDescription>,
+    pub player: Option<CharacterDescription>,
+    #[serde(default)]
+    pub npcs: Vec<NpcDescription>,
+    // Named places the player comes out at when entering the scene through a doorway, in the same
+    // space as the player's position.
+    #[serde(default)]
+    pub spawn_points: BTreeMap<String, Point>,
+    #[serde(default)]
+    pub doorways: Vec<DoorwayDescription>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneObjectDescription {
+    // Path of the asset as listed in `SceneDescription::assets`.
+    pub asset: String,
+    pub position: Point,
+    pub orientation: AssetOrientation,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterDescription {
+    // Path of the character definition, see `CharacterMetadata`.
+    pub character: String,
+    pub position: Point,
+}
+
+// Non-player character, driven by one of the AI behaviours.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NpcDescription {
+    // Path of the character definition, see `CharacterMetadata`.
+    pub character: String,
+    pub position: Point,
+    #[serde(default)]
+    pub behaviour: AiBehaviourDescription,
+}
+
+// Scene file counterpart of `AiBehaviour`. Distances and waypoints are in world units, and waypoints
+// are positions of the character's physics body.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AiBehaviourDescription {
+    #[default]
+    Idle,
+    Wander { radius: f32 },
+    Patrol { waypoints: Vec<Point> },
+    // Entities can't be referred to from scene files, so only the player can be followed.
+    FollowPlayer { distance: f32 },
+}
+
+// Area that takes the player to another scene when walked into.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DoorwayDescription {
+    // World-space rect of the area, by its top-left corner and size.
+    pub position: Point,
+    pub size: Point,
+    // Path of the scene the doorway leads to.
+    pub scene: String,
+    // Spawn point in that scene, see `SceneDescription::spawn_points`.
+    pub spawn_point: String,
+}
+
+pub fn load_scene_description(source: &dyn AssetSource, path: &str) -> anyhow::Result<SceneDescription> {
+    println!("loading scene description: {path}");
+
+    let data = source.read(path)?;
+    let input = serde_json::from_slice(&data)?;
+
+    Ok(input)
+}
+
+pub fn save_scene_description_to_file(path: &str, description: &SceneDescription) -> anyhow::Result<()> {
+    println!("saving scene description: {path}");
+
+    let file = File::create(path)?;
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, description)?;
+
+    Ok(())
+}
+
+impl SceneDescription {
+    // Paths of all the characters the scene refers to.
+    pub fn character_paths(&self) -> Vec<&str> {
+        let mut paths = self
+            .player
+            .iter()
+            .map(|player| player.character.as_str())
+            .chain(self.npcs.iter().map(|npc| npc.character.as_str()))
+            .collect::<Vec<_>>();
+
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+}
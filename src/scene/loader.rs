@@ -0,0 +1,120 @@
+use super::{description::*, scene::*, transition::*};
+use crate::asset::*;
+use std::sync::Arc;
+
+// Loads a scene without blocking the main thread, so that a loading screen can be shown meanwhile.
+// Call `poll` each frame until it returns `true`, and then `finish` to get the scene.
+pub struct SceneLoader {
+    description: SceneDescription,
+    assets: Vec<Asset>,
+    characters: Vec<CharacterAsset>,
+    images: ImageLoader,
+    // State the player brings along from the previous scene, if switching scenes.
+    player_state: Option<PlayerState>,
+}
+
+impl SceneLoader {
+    pub fn start(source: Arc<dyn AssetSource>, path: &str) -> anyhow::Result<Self> {
+        let description = load_scene_description(source.as_ref(), path)?;
+
+        Self::start_from_description(source, description, None)
+    }
+
+    // Loads the scene a doorway leads to, with the player coming out at the spawn point instead of
+    // wherever the scene file puts it, and keeping its state from the previous scene.
+    pub fn start_transition(
+        source: Arc<dyn AssetSource>,
+        transition: &SceneTransition,
+        player_state: PlayerState,
+    ) -> anyhow::Result<Self> {
+        let mut description = load_scene_description(source.as_ref(), &transition.scene)?;
+
+        let position = match description.spawn_points.get(&transition.spawn_point) {
+            Some(position) => *position,
+            None => anyhow::bail!(
+                "spawn point not found in {}: {}",
+                transition.scene,
+                transition.spawn_point
+            ),
+        };
+
+        description.player = Some(CharacterDescription {
+            character: player_state.character.clone(),
+            position,
+        });
+
+        Self::start_from_description(source, description, Some(player_state))
+    }
+
+    fn start_from_description(
+        source: Arc<dyn AssetSource>,
+        description: SceneDescription,
+        player_state: Option<PlayerState>,
+    ) -> anyhow::Result<Self> {
+        // Scene description, asset and character metadata are tiny, so they're loaded right away.
+        // Images are loaded in the background.
+        let asset_paths = description.assets.iter().map(String::as_str).collect::<Vec<_>>();
+        let assets = load_asset_bundle_headless(source.as_ref(), &asset_paths)?;
+        let characters = load_character_bundle(source.as_ref(), &description.character_paths())?;
+
+        let mut image_paths = vec![];
+        image_paths.extend(description.background.clone());
+
+        for asset in &assets {
+            image_paths.extend(asset_image_paths(&asset.metadata, &asset.path));
+        }
+
+        for character in &characters {
+            image_paths.extend(character_image_paths(character));
+        }
+
+        Ok(Self {
+            description,
+            assets,
+            characters,
+            images: ImageLoader::start(source, image_paths),
+            player_state,
+        })
+    }
+
+    // Uploads the images loaded so far. Returns `true` once everything is loaded.
+    pub fn poll(&mut self) -> anyhow::Result<bool> {
+        self.images.poll()
+    }
+
+    // Loading progress, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        match self.images.progress() {
+            (_, 0) => 1.0,
+            (loaded, total) => loaded as f32 / total as f32,
+        }
+    }
+
+    pub fn finish(self) -> anyhow::Result<Scene> {
+        if !self.images.is_done() {
+            anyhow::bail!("scene is not loaded yet");
+        }
+
+        let textures = self.images.into_textures();
+        let get_texture = |path: &str| match textures.get(path) {
+            Some(texture) => Ok(texture.clone()),
+            None => anyhow::bail!("texture not loaded: {path}"),
+        };
+
+        let mut assets = self.assets;
+
+        for asset in &mut assets {
+            asset.resources = Some(load_asset_resources(&asset.metadata, &asset.path, get_texture)?);
+        }
+
+        let mut scene = Scene::from_description(&self.description, assets, self.characters, |path| {
+            get_texture(path).map(Some)
+        })?;
+
+        if let Some(player_state) = &self.player_state {
+            scene.restore_player_state(player_state)?;
+        }
+
+        Ok(scene)
+    }
+}
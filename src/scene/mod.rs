@@ -1,5 +1,13 @@
+mod ai;
 mod components;
+mod description;
+mod loader;
 mod scene;
+mod transition;
 
+pub use ai::*;
 pub use components::*;
+pub use description::*;
+pub use loader::*;
 pub use scene::*;
+pub use transition::*;
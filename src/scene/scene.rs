@@ -1,20 +1,82 @@
-use super::components::*;
+use super::{ai::*, components::*, description::*, transition::*};
 use crate::asset::*;
 use crate::math::*;
+use crate::navigation::*;
 use crate::physics::*;
 use crate::render::*;
 use hecs::*;
 use macroquad::prelude::*;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// Characters are considered to have reached a waypoint this close to it, in world units.
+const WAYPOINT_RADIUS: f32 = 1.0;
+
+// Extra room paths leave between characters and obstacles, so that characters don't scrape along
+// them, in world units.
+const NAV_CLEARANCE: f32 = 1.0;
+
+// Scenes without a map (e.g. headless ones) can be navigated this far from the origin, in world
+// units.
+const NAV_DEFAULT_AREA_HALF_SIZE: f32 = 1000.0;
+
+// Walls along the room bounds are this thick, so that characters can't tunnel through them, and
+// this tall, so that they stop characters of any height, in world units.
+const BOUNDS_WALL_THICKNESS: f32 = 50.0;
+const BOUNDS_WALL_HEIGHT: f32 = 200.0;
+
+// Doorway sensors are this tall, so that characters of any height walk into them, in world units.
+const DOORWAY_HEIGHT: f32 = 200.0;
 
 pub struct CharacterSpawnParams {
-    pub offset: Vec2,
-    pub front_point: Vec2,
-    pub texture: TextureResource,
+    pub sprites: HashMap<AssetOrientation, CharacterSprite>,
+    // Direction the character faces when spawned.
+    pub facing: AssetOrientation,
     pub position: Vec2,
+    pub movement_speed: f32,
     pub physics_body_origin: Vec3,
     pub physics_collider_half_extent: Vec3,
 }
 
+impl CharacterSpawnParams {
+    // Spawn parameters of a character from its definition. Textures of the character's sprites are
+    // provided by `load_texture` given image paths.
+    pub fn from_character(
+        character: &CharacterAsset,
+        position: Vec2,
+        load_texture: impl Fn(&str) -> anyhow::Result<Option<TextureResource>>,
+    ) -> anyhow::Result<Self> {
+        let metadata = &character.metadata;
+        let (facing, _) = metadata.default_direction();
+
+        let mut sprites = HashMap::new();
+
+        for (direction, image) in &metadata.directions {
+            let sprite = CharacterSprite {
+                texture: load_texture(&resolve_image_path(&image.url, &character.path))?,
+                offset: image.transform.position.vec2(),
+                front_point: image.transform.front_point.vec2(),
+                clips: image.clips.clone(),
+            };
+
+            sprites.insert(*direction, sprite);
+        }
+
+        let collider = &metadata.collider;
+        let half_extent_2d = collider.shape.scale.vec2() * 0.5;
+        let half_extent = half_extent_2d.extend(collider.height * 0.5);
+
+        Ok(Self {
+            sprites,
+            facing,
+            position,
+            movement_speed: metadata.movement_speed,
+            // Body origin is the center of the collider, relative to the character position.
+            physics_body_origin: (collider.shape.position.vec2() + half_extent_2d).extend(half_extent.z),
+            physics_collider_half_extent: half_extent,
+        })
+    }
+}
+
 // This is a rudimentary scene representation. Since there's not a whole lot going on currently, it
 // takes care of everything - from physics to rendering. If/when the code base grows, we'd likely
 // split it into smaller pieces. For now though, this should be fine.
@@ -23,7 +85,25 @@ pub struct Scene {
     physics: PhysicsWorld,
     scale: f32,
     background_draw_call: Option<SpriteDrawCall>,
+    background_path: Option<String>,
     player_character: Option<Entity>,
+    debug_draw: DebugDrawSettings,
+    shadow_map: ShadowMap,
+    // Room bounds set by the scene file, if any. Otherwise the bounds are derived from the map.
+    bounds: Option<Vec<Vec2>>,
+    // Static body with the walls along the room bounds, if the scene has any.
+    bounds_body: Option<RigidBodyHandle>,
+    // Where characters can walk. Kept up to date as static objects are spawned, moved and
+    // despawned.
+    walkability: WalkabilityMap,
+    camera: SceneCamera,
+    // Assets available for spawning objects at runtime, e.g. from the level editor.
+    assets: Vec<Asset>,
+    characters: Vec<CharacterAsset>,
+    // Where the player comes out when entering the scene through a doorway, kept for saving.
+    spawn_points: BTreeMap<String, Point>,
+    // Set when the player walks into a doorway, see `take_transition`.
+    transition: Option<SceneTransition>,
 }
 
 impl Scene {
@@ -38,21 +118,294 @@ impl Scene {
             ..Default::default()
         });
 
-        Self {
+        // Keep the view on the map.
+        let camera = SceneCamera {
+            bounds: background_draw_call
+                .as_ref()
+                .map(|dc| Rect::new(dc.origin.x, dc.origin.y, dc.extent.x, dc.extent.y)),
+            texel_size: scale,
+            ..Default::default()
+        };
+
+        // Without a map, characters can walk anywhere, see `set_map_size`.
+        let half_size = NAV_DEFAULT_AREA_HALF_SIZE;
+        let area = vec![
+            vec2(-half_size, -half_size),
+            vec2(half_size, -half_size),
+            vec2(half_size, half_size),
+            vec2(-half_size, half_size),
+        ];
+        let map_size = background_draw_call.as_ref().map(|dc| dc.extent);
+
+        let mut result = Self {
             world,
             physics,
             scale,
             background_draw_call,
+            background_path: None,
             player_character,
+            bounds: None,
+            bounds_body: None,
+            debug_draw: DebugDrawSettings::default(),
+            shadow_map: ShadowMap::default(),
+            walkability: WalkabilityMap::new(area, Vec2::ZERO),
+            camera,
+            assets: vec![],
+            characters: vec![],
+            spawn_points: BTreeMap::new(),
+            transition: None,
+        };
+
+        if let Some(map_size) = map_size {
+            result.set_map_size(map_size);
+        }
+
+        result
+    }
+
+    // Keeps characters on the map of the given screen-space size, with its top left corner at the
+    // origin. Characters can walk anywhere on the map, and walls keep them from walking off of it.
+    // The map is a rect on screen, which makes it a parallelogram in world space. Scenes can set
+    // other bounds, see `set_bounds`.
+    fn set_map_size(&mut self, size: Vec2) {
+        let area = [Vec2::ZERO, vec2(size.x, 0.0), size, vec2(0.0, size.y)]
+            .iter()
+            .map(|x| screen_to_world(*x))
+            .collect();
+
+        self.set_walls(area);
+    }
+
+    // Loads a scene file: creates the scene, loads the asset bundle it references, spawns all the
+    // static objects and the player, and finally initializes the scene.
+    pub fn load_from_file(path: &str) -> anyhow::Result<Self> {
+        Self::load_from_source(&default_asset_source(), path, false)
+    }
+
+    // Loads a scene file without any textures. Such a scene can be simulated without a window, but
+    // won't render anything.
+    pub fn load_headless_from_file(path: &str) -> anyhow::Result<Self> {
+        Self::load_from_source(&default_asset_source(), path, true)
+    }
+
+    pub fn load_from_source(source: &dyn AssetSource, path: &str, headless: bool) -> anyhow::Result<Self> {
+        let description = load_scene_description(source, path)?;
+
+        let asset_paths = description.assets.iter().map(String::as_str).collect::<Vec<_>>();
+        let assets = if headless {
+            load_asset_bundle_headless(source, &asset_paths)?
+        } else {
+            load_asset_bundle(source, &asset_paths)?
+        };
+
+        let characters = load_character_bundle(source, &description.character_paths())?;
+
+        let mut scene = Self::from_description(&description, assets, characters, |path| {
+            if headless {
+                Ok(None)
+            } else {
+                load_texture(source, path).map(Some)
+            }
+        })?;
+
+        // Headless scenes have no background texture to take the map size from, so read it from
+        // the image itself, for the same walls as with a window.
+        match &description.background {
+            Some(background) if headless && description.bounds.is_none() => {
+                scene.set_map_size(load_image_size(source, background)? * scene.scale);
+            }
+            _ => {}
+        }
+
+        Ok(scene)
+    }
+
+    // Builds a scene out of its description and already loaded assets and characters. Textures
+    // which are not part of the assets (i.e. background and character textures) are provided by
+    // `load_texture`.
+    pub fn from_description(
+        description: &SceneDescription,
+        assets: Vec<Asset>,
+        characters: Vec<CharacterAsset>,
+        load_texture: impl Fn(&str) -> anyhow::Result<Option<TextureResource>>,
+    ) -> anyhow::Result<Self> {
+        let background_tex = match &description.background {
+            Some(background) => load_texture(background)?,
+            None => None,
+        };
+
+        let mut scene = Self::new(description.scale, background_tex);
+        scene.camera.scale_policy = description.view_scale;
+        scene.background_path = description.background.clone();
+        scene.assets = assets;
+
+        if let Some(bounds) = &description.bounds {
+            scene.set_bounds(bounds.iter().map(Point::vec2).collect())?;
+        }
+
+        for object in &description.objects {
+            scene.spawn_bundled_object(&object.asset, object.position.vec2(), object.orientation)?;
+        }
+
+        scene.spawn_points = description.spawn_points.clone();
+
+        for doorway in &description.doorways {
+            scene.spawn_doorway(doorway.clone());
+        }
+
+        scene.characters = characters;
+
+        if let Some(player) = &description.player {
+            let character = match scene.characters.iter().find(|x| x.path == player.character) {
+                Some(character) => character,
+                None => anyhow::bail!("character not loaded: {}", player.character),
+            };
+
+            let params = CharacterSpawnParams::from_character(character, player.position.vec2(), &load_texture)?;
+            scene.spawn_player(params);
+
+            // Remember the description the player was spawned from, so that it can be saved.
+            if let Some(player_character) = scene.player_character {
+                scene.world.insert_one(
+                    player_character,
+                    CharacterSourceComponent {
+                        description: player.clone(),
+                    },
+                )?;
+            }
+        }
+
+        // NPCs are spawned after the player, so that they can follow it.
+        for npc in &description.npcs {
+            let character = match scene.characters.iter().find(|x| x.path == npc.character) {
+                Some(character) => character,
+                None => anyhow::bail!("character not loaded: {}", npc.character),
+            };
+
+            let params = CharacterSpawnParams::from_character(character, npc.position.vec2(), &load_texture)?;
+            let behaviour = match &npc.behaviour {
+                AiBehaviourDescription::Idle => AiBehaviour::Idle,
+                AiBehaviourDescription::Wander { radius } => AiBehaviour::Wander { radius: *radius },
+                AiBehaviourDescription::Patrol { waypoints } => AiBehaviour::Patrol {
+                    waypoints: waypoints.iter().map(Point::vec2).collect(),
+                },
+                AiBehaviourDescription::FollowPlayer { distance } => match scene.player_character {
+                    Some(target) => AiBehaviour::Follow {
+                        target,
+                        distance: *distance,
+                    },
+                    None => anyhow::bail!("npc follows the player, but there's no player: {}", npc.character),
+                },
+            };
+
+            let entity = scene.spawn_npc(params, behaviour)?;
+            scene.world.insert_one(
+                entity,
+                NpcSourceComponent {
+                    description: npc.clone(),
+                },
+            )?;
+        }
+
+        // Finish scene loading.
+        scene.initialize();
+
+        Ok(scene)
+    }
+
+    // Saves the scene into a file that can later be loaded with `Scene::load_from_file`.
+    pub fn save(&mut self, path: &str) -> anyhow::Result<()> {
+        save_scene_description_to_file(path, &self.to_description())
+    }
+
+    // Walks the world and builds a scene description out of the placed objects and the player.
+    pub fn to_description(&mut self) -> SceneDescription {
+        // Keep the whole bundle, even if some of the assets are not placed anywhere (yet).
+        let mut assets = self.assets.iter().map(|asset| asset.path.clone()).collect::<Vec<_>>();
+        let mut objects = vec![];
+
+        for (_, (root_transform, object)) in self
+            .world
+            .query_mut::<(&RootTransformComponent, &SceneObjectComponent)>()
+        {
+            assets.push(object.asset_path.clone());
+            objects.push(SceneObjectDescription {
+                asset: object.asset_path.clone(),
+                position: root_transform.position.into(),
+                orientation: object.orientation,
+            });
+        }
+
+        assets.sort();
+        assets.dedup();
+
+        let player = self.player_character.and_then(|player_character| {
+            let source = self.world.get::<CharacterSourceComponent>(player_character).ok()?;
+            let phys_body_comp = self.world.get::<PhysicsBodyComponent>(player_character).ok()?;
+            let mut description = source.description.clone();
+            description.position = self.character_position(&description.character, phys_body_comp.handle)?;
+
+            Some(description)
+        });
+
+        let npcs = self
+            .world
+            .query::<(&NpcSourceComponent, &PhysicsBodyComponent)>()
+            .iter()
+            .filter_map(|(_, (source, phys_body_comp))| {
+                let mut description = source.description.clone();
+                description.position = self.character_position(&description.character, phys_body_comp.handle)?;
+
+                Some(description)
+            })
+            .collect();
+
+        let doorways = self
+            .world
+            .query::<&DoorwayComponent>()
+            .iter()
+            .map(|(_, doorway)| doorway.description.clone())
+            .collect();
+
+        SceneDescription {
+            scale: self.scale,
+            view_scale: self.camera.scale_policy,
+            background: self.background_path.clone(),
+            bounds: self
+                .bounds
+                .as_ref()
+                .map(|bounds| bounds.iter().map(|x| Point::from(*x)).collect()),
+            assets,
+            objects,
+            player,
+            npcs,
+            spawn_points: self.spawn_points.clone(),
+            doorways,
         }
     }
 
+    // Position of a character as it'd be spawned from a scene file, given its physics body.
+    fn character_position(&self, character_path: &str, handle: RigidBodyHandle) -> Option<Point> {
+        // The physics body is offset from the character position by the body origin, see
+        // `spawn_character`.
+        let character = self.characters.iter().find(|x| x.path == character_path)?;
+        let collider = &character.metadata.collider;
+        let body_offset = (collider.shape.position.vec2() + collider.shape.scale.vec2() * 0.5) * self.scale;
+
+        Some((self.physics.get_body_translation_2d(handle) - body_offset).into())
+    }
+
     // Initialize should be called after all the static objects have been spawned (a.k.a. at the end
     // of scene loading) to precompute static object draw calls and finish any pending
     // initialization.
     pub fn initialize(&mut self) {
         // Update draw calls for static objects so we don't have to do it each frame.
         init_static_scene_objects(&mut self.world);
+        self.shadow_map.invalidate();
+    }
+
+    pub fn assets(&self) -> &[Asset] {
+        &self.assets
     }
 
     pub fn spawn_static_object(
@@ -60,32 +413,305 @@ impl Scene {
         asset: &Asset,
         position: Vec2,
         orientation: AssetOrientation,
-    ) -> anyhow::Result<()> {
-        spawn_static_scene_object(
+    ) -> anyhow::Result<Entity> {
+        self.shadow_map.invalidate();
+
+        let object = spawn_static_scene_object(
             &mut self.world,
             &mut self.physics,
             asset,
             position,
             self.scale,
             orientation,
-        )
+        )?;
+
+        self.walkability.add_obstacle(
+            object,
+            static_object_footprints(asset, position, self.scale, orientation)?,
+        );
+
+        Ok(object)
+    }
+
+    // Spawns a static object using one of the assets from the scene's bundle. Draw calls are
+    // updated right away, so this can also be used after the scene has been initialized.
+    pub fn spawn_bundled_object(
+        &mut self,
+        asset_path: &str,
+        position: Vec2,
+        orientation: AssetOrientation,
+    ) -> anyhow::Result<Entity> {
+        let asset = match self.assets.iter().find(|asset| asset.path == asset_path) {
+            Some(asset) => asset,
+            None => anyhow::bail!("asset not in the scene bundle: {asset_path}"),
+        };
+
+        let object = spawn_static_scene_object(
+            &mut self.world,
+            &mut self.physics,
+            asset,
+            position,
+            self.scale,
+            orientation,
+        )?;
+
+        update_static_scene_object(&mut self.world, object)?;
+        self.shadow_map.invalidate();
+        self.walkability.add_obstacle(
+            object,
+            static_object_footprints(asset, position, self.scale, orientation)?,
+        );
+
+        Ok(object)
+    }
+
+    // Removes a static object along with all of its image entities and physics bodies.
+    pub fn despawn_object(&mut self, object: Entity) -> anyhow::Result<()> {
+        self.shadow_map.invalidate();
+        self.walkability.remove_obstacle(object);
+        despawn_static_scene_object(&mut self.world, &mut self.physics, object)
+    }
+
+    // Moves a static object to a new position, along with its draw calls and physics bodies.
+    pub fn move_object(&mut self, object: Entity, position: Vec2) -> anyhow::Result<()> {
+        self.drag_object(object, position)?;
+        self.shadow_map.invalidate();
+
+        Ok(())
+    }
+
+    // Same as `move_object`, but keeps the shadow map as is, since rebaking it on every frame of
+    // dragging an object around is way too slow. Call `end_drag` once the object is dropped.
+    pub fn drag_object(&mut self, object: Entity, position: Vec2) -> anyhow::Result<()> {
+        let offset = position - self.world.get::<RootTransformComponent>(object)?.position;
+        self.walkability.move_obstacle(object, offset);
+
+        move_static_scene_object(&mut self.world, &mut self.physics, object, position)
+    }
+
+    // Brings shadows up to date with objects moved by `drag_object`.
+    pub fn end_drag(&mut self) {
+        self.shadow_map.invalidate();
+    }
+
+    // Replaces the room bounds with the given world-space polygon, which characters can't leave.
+    pub fn set_bounds(&mut self, bounds: Vec<Vec2>) -> anyhow::Result<()> {
+        if bounds.len() < 3 {
+            anyhow::bail!("room bounds need at least 3 points, got {}", bounds.len());
+        }
+
+        self.set_walls(bounds.clone());
+        self.bounds = Some(bounds);
+
+        Ok(())
+    }
+
+    // Replaces the walls characters can't leave, and where they can walk accordingly.
+    fn set_walls(&mut self, area: Vec<Vec2>) {
+        if let Some(bounds_body) = self.bounds_body.take() {
+            self.physics.remove_body(bounds_body);
+        }
+
+        self.bounds_body = Some(
+            self.physics
+                .create_body_boundary(&area, BOUNDS_WALL_THICKNESS, BOUNDS_WALL_HEIGHT),
+        );
+        self.walkability.set_area(area);
+    }
+
+    pub fn walkability(&self) -> &WalkabilityMap {
+        &self.walkability
+    }
+
+    pub fn describe_object(&self, object: Entity) -> Option<SceneObjectDescription> {
+        let root_transform = self.world.get::<RootTransformComponent>(object).ok()?;
+        let source = self.world.get::<SceneObjectComponent>(object).ok()?;
+
+        Some(SceneObjectDescription {
+            asset: source.asset_path.clone(),
+            position: root_transform.position.into(),
+            orientation: source.orientation,
+        })
+    }
+
+    // Finds the top-most static object whose sprite covers the given screen position. Mouse
+    // positions need to go through `SceneCamera::window_to_screen` first.
+    pub fn pick_object(&mut self, screen_position: Vec2) -> Option<Entity> {
+        let mut result = None;
+        let mut result_order = f32::MIN;
+
+        for (_, (part, dc)) in self
+            .world
+            .query_mut::<(&SceneObjectPartComponent, &SpriteDrawCallComponent)>()
+        {
+            let dc = &dc.draw_call;
+            let bounds = Rect::new(dc.origin.x, dc.origin.y, dc.extent.x, dc.extent.y);
+
+            if bounds.contains(screen_position) && dc.order > result_order {
+                result = Some(part.object);
+                result_order = dc.order;
+            }
+        }
+
+        result
+    }
+
+    // Screen-space bounds of all the sprites of a static object.
+    pub fn object_screen_bounds(&self, object: Entity) -> Option<Rect> {
+        let source = self.world.get::<SceneObjectComponent>(object).ok()?;
+        let mut min = vec2(f32::MAX, f32::MAX);
+        let mut max = vec2(f32::MIN, f32::MIN);
+
+        for part in &source.parts {
+            if let Ok(dc) = self.world.get::<SpriteDrawCallComponent>(*part) {
+                min = min.min(dc.draw_call.origin);
+                max = max.max(dc.draw_call.origin + dc.draw_call.extent);
+            }
+        }
+
+        if min.x <= max.x && min.y <= max.y {
+            Some(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+        } else {
+            None
+        }
     }
 
     pub fn spawn_player(&mut self, params: CharacterSpawnParams) {
-        self.player_character = Some(spawn_character(&mut self.world, &mut self.physics, self.scale, params));
+        let position = params.position;
+        let player_character = spawn_character(&mut self.world, &mut self.physics, self.scale, params);
+
+        self.player_character = Some(player_character);
+
+        // Walkability is for characters of the player's size, with a bit of room to spare.
+        let half_extent = self
+            .world
+            .get::<CharacterPathComponent>(player_character)
+            .map_or(Vec2::ZERO, |path| path.half_extent.truncate());
+        self.walkability.set_agent_half_extent(half_extent + NAV_CLEARANCE);
+
+        // Follow the player, starting right at it instead of panning over from wherever the camera
+        // was.
+        self.camera.target = Some(player_character);
+        self.camera.following = true;
+        self.camera.position = world_to_screen(position);
+    }
+
+    // Spawns a non-player character driven by the given behaviour. NPCs have the same dynamic physics
+    // bodies as the player, so they bump into it and into each other.
+    pub fn spawn_npc(&mut self, params: CharacterSpawnParams, behaviour: AiBehaviour) -> anyhow::Result<Entity> {
+        let npc = spawn_character(&mut self.world, &mut self.physics, self.scale, params);
+
+        // Wandering is relative to where the body starts, since that's what the AI steers.
+        let handle = self.world.get::<PhysicsBodyComponent>(npc)?.handle;
+        let home = self.physics.get_body_translation_2d(handle);
+        self.world.insert_one(npc, AiComponent::new(behaviour, home))?;
+
+        Ok(npc)
+    }
+
+    // Spawns a doorway to another scene, see `take_transition`.
+    pub fn spawn_doorway(&mut self, description: DoorwayDescription) -> Entity {
+        let half_extent = (description.size.vec2() * 0.5).extend(DOORWAY_HEIGHT * 0.5);
+        let origin = description.position.vec2().extend(0.0) + half_extent;
+        let handle = self
+            .physics
+            .create_body_sensor_cuboid(RigidBodyType::Static, origin, half_extent);
+
+        self.world.spawn((
+            // Assume the player is inside until the next update tells otherwise, so that a player
+            // spawned in a doorway isn't sent right back.
+            DoorwayComponent {
+                description,
+                player_inside: true,
+            },
+            PhysicsBodyComponent { handle },
+        ))
     }
 
-    // Update player movement speed whenever it changes, or simply each frame.
-    pub fn set_player_movement_input(&mut self, input: Vec2, move_speed: f32) {
+    // Scene the player asked to go to by walking into a doorway since the last call, if any. The
+    // scene doesn't switch by itself, since loading the other scene is up to the caller, see
+    // `SceneLoader::start_transition`.
+    pub fn take_transition(&mut self) -> Option<SceneTransition> {
+        self.transition.take()
+    }
+
+    // State the player keeps when switching scenes, see `restore_player_state`.
+    pub fn player_state(&self) -> Option<PlayerState> {
+        let player_character = self.player_character?;
+        let source = self.world.get::<CharacterSourceComponent>(player_character).ok()?;
+        let sprites = self.world.get::<CharacterSpritesComponent>(player_character).ok()?;
+
+        Some(PlayerState {
+            character: source.description.character.clone(),
+            facing: sprites.facing,
+        })
+    }
+
+    // Applies the state the player had in the previous scene.
+    pub fn restore_player_state(&mut self, state: &PlayerState) -> anyhow::Result<()> {
+        let player_character = match self.player_character {
+            Some(player_character) => player_character,
+            None => anyhow::bail!("scene has no player"),
+        };
+
+        let (sprites, animation) = self
+            .world
+            .query_one_mut::<(&mut CharacterSpritesComponent, &mut AnimationComponent)>(player_character)?;
+
+        sprites.face(state.facing);
+
+        if let Some(sprite) = sprites.current() {
+            animation.set_clips(sprite.clips.clone());
+        }
+
+        Ok(())
+    }
+
+    // Position of a character's physics body on the ground plane.
+    pub fn character_body_position(&self, character: Entity) -> Option<Vec2> {
+        let phys_body_comp = self.world.get::<PhysicsBodyComponent>(character).ok()?;
+
+        Some(self.physics.get_body_translation_2d(phys_body_comp.handle))
+    }
+
+    pub fn camera(&self) -> &SceneCamera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut SceneCamera {
+        &mut self.camera
+    }
+
+    pub fn debug_draw_settings_mut(&mut self) -> &mut DebugDrawSettings {
+        &mut self.debug_draw
+    }
+
+    // Update player movement input whenever it changes, or simply each frame. The player moves at
+    // the speed set in its character definition.
+    pub fn set_player_movement_input(&mut self, input: Vec2) {
         if let Some(player_character) = self.player_character {
             // Update character desired velocity, based on input.
-            let (vel_comp, phys_body_comp) = self
+            let (vel_comp, path, phys_body_comp) = self
                 .world
-                .query_one_mut::<(&mut CharacterVelocityComponent, &PhysicsBodyComponent)>(player_character)
+                .query_one_mut::<(
+                    &mut CharacterVelocityComponent,
+                    &mut CharacterPathComponent,
+                    &PhysicsBodyComponent,
+                )>(player_character)
                 .unwrap();
 
-            // Store velocity so we can later use it to determine sprite facing direction.
-            vel_comp.velocity = screen_to_world(input) * move_speed;
+            // Input takes over from walking along a path, but no input shouldn't stop the player
+            // from walking where it was sent.
+            if input == Vec2::ZERO && !path.waypoints.is_empty() {
+                return;
+            }
+
+            path.waypoints.clear();
+
+            // Store velocity so we can later use it to determine sprite facing direction. Screen
+            // directions map to world directions of different lengths, so normalize again to walk
+            // equally fast in all of them.
+            vel_comp.velocity = screen_to_world(input).normalize_or_zero() * vel_comp.movement_speed;
 
             // Set physics body linear velocity.
             self.physics
@@ -93,21 +719,175 @@ impl Scene {
         }
     }
 
-    // Runs the full scene update. Takes care of character movement, physics and rendering.
+    // Stops the player where it is, dropping the path it was walking along.
+    fn stop_player(&mut self) {
+        let player_character = match self.player_character {
+            Some(player_character) => player_character,
+            None => return,
+        };
+
+        if let Ok((vel_comp, path, phys_body_comp)) = self.world.query_one_mut::<(
+            &mut CharacterVelocityComponent,
+            &mut CharacterPathComponent,
+            &PhysicsBodyComponent,
+        )>(player_character)
+        {
+            path.waypoints.clear();
+            vel_comp.velocity = Vec2::ZERO;
+            self.physics
+                .set_body_linear_velocity_2d(phys_body_comp.handle, vel_comp.velocity);
+        }
+    }
+
+    // Sends the player walking to the given world position, around any static objects in the way.
+    // Returns `false` if there's no way there.
+    pub fn move_player_to(&mut self, target: Vec2) -> bool {
+        let (player_character, position) = match (self.player_character, self.player_position()) {
+            (Some(player_character), Some(position)) => (player_character, position),
+            _ => return false,
+        };
+
+        let waypoints = match self.walkability.find_path(position, target) {
+            Some(waypoints) => waypoints,
+            None => return false,
+        };
+
+        if let Ok(mut path) = self.world.get_mut::<CharacterPathComponent>(player_character) {
+            path.waypoints = VecDeque::from(waypoints);
+        }
+
+        true
+    }
+
+    // Position of the player's physics body on the ground plane.
+    pub fn player_position(&self) -> Option<Vec2> {
+        self.character_body_position(self.player_character?)
+    }
+
+    // Runs the full scene update. Takes care of character movement, physics, the camera and
+    // rendering.
     pub fn update(&mut self, dt: f32) {
+        self.simulate(dt);
+        self.update_camera(dt);
+        self.render();
+    }
+
+    fn update_camera(&mut self, dt: f32) {
+        // Window size and DPI may change at any time, e.g. when the window is moved to another
+        // display, and the camera rescales the view accordingly.
+        self.camera
+            .set_viewport(vec2(screen_width(), screen_height()), screen_dpi_scale());
+
+        if self.camera.following {
+            let target = self
+                .camera
+                .target
+                .and_then(|target| self.world.get::<RootTransformComponent>(target).ok())
+                .map(|root_transform| world_to_screen(root_transform.position));
+
+            if let Some(target) = target {
+                self.camera.follow(target, dt);
+            }
+        }
+
+        self.camera.clamp_to_bounds();
+    }
+
+    // Runs the simulation part of the scene update, without any rendering. This doesn't need a
+    // window, so it can be used for headless scenes.
+    pub fn simulate(&mut self, dt: f32) {
+        self.update_ai(dt);
+        self.follow_paths(dt);
+
         // Run physics simulation.
         self.physics.update(dt);
         self.update_dynamic_objects();
-        self.render();
+        self.update_doorways();
+        self.update_animations(dt);
+    }
+
+    // Ticks the behaviours of NPCs, which send them along paths for `follow_paths` to steer them
+    // through.
+    fn update_ai(&mut self, dt: f32) {
+        // Look up the positions of followed entities first, since they can't be queried while the
+        // AI components are borrowed.
+        let mut targets = HashMap::new();
+
+        for (_, ai) in self.world.query::<&AiComponent>().iter() {
+            if let Some(target) = ai.follow_target() {
+                if let Ok(phys_body_comp) = self.world.get::<PhysicsBodyComponent>(target) {
+                    targets.insert(target, self.physics.get_body_translation_2d(phys_body_comp.handle));
+                }
+            }
+        }
+
+        for (_, (ai, path, vel_comp, phys_body_comp)) in self.world.query_mut::<(
+            &mut AiComponent,
+            &mut CharacterPathComponent,
+            &mut CharacterVelocityComponent,
+            &PhysicsBodyComponent,
+        )>() {
+            let position = self.physics.get_body_translation_2d(phys_body_comp.handle);
+            let target_position = ai.follow_target().and_then(|target| targets.get(&target).copied());
+            let walking = !path.waypoints.is_empty();
+
+            if let Some(waypoints) = ai.tick(position, walking, target_position, &self.walkability, dt) {
+                path.waypoints = VecDeque::from(waypoints);
+            }
+
+            // `follow_paths` leaves characters without a path alone, so stop them here.
+            if path.waypoints.is_empty() {
+                vel_comp.velocity = Vec2::ZERO;
+                self.physics
+                    .set_body_linear_velocity_2d(phys_body_comp.handle, vel_comp.velocity);
+            }
+        }
+    }
+
+    // Steers characters walking along paths towards their next waypoint.
+    fn follow_paths(&mut self, dt: f32) {
+        for (_, (path, vel_comp, phys_body_comp)) in self.world.query_mut::<(
+            &mut CharacterPathComponent,
+            &mut CharacterVelocityComponent,
+            &PhysicsBodyComponent,
+        )>() {
+            if path.waypoints.is_empty() {
+                continue;
+            }
+
+            let position = self.physics.get_body_translation_2d(phys_body_comp.handle);
+
+            while let Some(waypoint) = path.waypoints.front() {
+                if waypoint.distance(position) > WAYPOINT_RADIUS {
+                    break;
+                }
+
+                path.waypoints.pop_front();
+            }
+
+            // Don't go faster than it takes to reach the waypoint within the step, so that the
+            // character doesn't overshoot it.
+            vel_comp.velocity = match path.waypoints.front() {
+                Some(waypoint) => {
+                    let offset = *waypoint - position;
+                    let speed = vel_comp.movement_speed.min(offset.length() / dt.max(f32::EPSILON));
+                    offset.normalize_or_zero() * speed
+                }
+                None => Vec2::ZERO,
+            };
+
+            self.physics
+                .set_body_linear_velocity_2d(phys_body_comp.handle, vel_comp.velocity);
+        }
     }
 
     fn update_dynamic_objects(&mut self) {
-        // Update characters' transforms and draw calls. There's only the player's character
-        // right now, but let's pretend we have a bunch of dynamic characters.
-        for (_, (root_transform, sprite_transform, vel_comp, draw_call_comp, phys_body_comp)) in
+        // Update characters' transforms and draw calls, both the player's and NPCs'.
+        for (_, (root_transform, sprites, animation, vel_comp, draw_call_comp, phys_body_comp)) in
             self.world.query_mut::<(
                 &mut RootTransformComponent,
-                &CharacterTransformComponent,
+                &mut CharacterSpritesComponent,
+                &mut AnimationComponent,
                 &CharacterVelocityComponent,
                 &mut SpriteDrawCallComponent,
                 &PhysicsBodyComponent,
@@ -116,36 +896,139 @@ impl Scene {
             // Update character position based on physics simulation results.
             root_transform.position = self.physics.get_body_translation_2d(phys_body_comp.handle);
 
-            let screen_movement_dir = world_to_screen(vel_comp.velocity);
+            // Face the movement direction. Idle characters keep facing wherever they went last.
+            let previous_sprite = sprites.sprite;
+            if let Some(direction) = AssetOrientation::from_direction(vel_comp.velocity) {
+                sprites.face(direction);
+            }
+
+            let sprite = match sprites.current() {
+                Some(sprite) => sprite,
+                None => continue,
+            };
+
+            if sprites.sprite != previous_sprite {
+                animation.set_clips(sprite.clips.clone());
+            }
 
-            // Flip character sprite according to screen-space movement direction.
-            if screen_movement_dir.x != 0.0 {
-                draw_call_comp.draw_call.flip_x = if screen_movement_dir.x > 0.0 { true } else { false }
+            // Walk while moving, with the walk cycle sped up or slowed down to match the speed the
+            // body actually moved at, e.g. slower when sliding along a wall, so that feet don't
+            // slide over the floor.
+            let speed = self.physics.get_body_linear_velocity_2d(phys_body_comp.handle).length();
+            if speed > f32::EPSILON {
+                animation.play(WALK_CLIP);
+                animation.speed = speed / vel_comp.movement_speed;
+            } else {
+                animation.play(IDLE_CLIP);
+                animation.speed = 1.0;
             }
 
+            draw_call_comp.draw_call.texture = sprite.texture.clone();
+            draw_call_comp.draw_call.flip_x = sprites.flip_x;
+
             update_sprite_draw_call(
                 &mut draw_call_comp.draw_call,
                 root_transform.position,
                 root_transform.scale,
-                sprite_transform.offset,
-                sprite_transform.front_point,
+                sprite.offset,
+                sprite.front_point,
             );
         }
+
+        // Move shadows along with their bodies. Not only characters can have those.
+        for (_, (shadow, phys_body_comp)) in self
+            .world
+            .query_mut::<(&mut DynamicShadowComponent, &PhysicsBodyComponent)>()
+        {
+            shadow.draw_call.center = self.physics.get_body_translation_2d(phys_body_comp.handle);
+        }
+    }
+
+    // Raises a transition when the player walks into a doorway.
+    fn update_doorways(&mut self) {
+        let player_body = match self
+            .player_character
+            .and_then(|player_character| self.world.get::<PhysicsBodyComponent>(player_character).ok())
+        {
+            Some(phys_body_comp) => phys_body_comp.handle,
+            None => return,
+        };
+
+        let mut entered = false;
+
+        for (_, (doorway, phys_body_comp)) in self.world.query_mut::<(&mut DoorwayComponent, &PhysicsBodyComponent)>() {
+            let inside = self.physics.bodies_intersect(phys_body_comp.handle, player_body);
+
+            if inside && !doorway.player_inside && self.transition.is_none() {
+                self.transition = Some(SceneTransition {
+                    scene: doorway.description.scene.clone(),
+                    spawn_point: doorway.description.spawn_point.clone(),
+                });
+                entered = true;
+            }
+
+            doorway.player_inside = inside;
+        }
+
+        // The player stays in the doorway while the next scene loads, rather than walking on along
+        // a path it was sent on.
+        if entered {
+            self.stop_player();
+        }
+    }
+
+    // Advances sprite animations and points draw calls to their current frames.
+    fn update_animations(&mut self, dt: f32) {
+        for (_, (animation, root_transform, draw_call_comp)) in self.world.query_mut::<(
+            &mut AnimationComponent,
+            &RootTransformComponent,
+            &mut SpriteDrawCallComponent,
+        )>() {
+            animation.advance(dt);
+
+            let source = animation.current_frame().map(|frame| frame.rect());
+            draw_call_comp.draw_call.set_source(source, root_transform.scale);
+        }
     }
 
-    fn render(&mut self) {
+    pub fn render(&mut self) {
         clear_background(BLACK);
 
+        // Everything in the scene is drawn through the camera.
+        self.camera.apply();
+
         // Render scene background.
         if let Some(background_draw_call) = &self.background_draw_call {
             background_draw_call.draw();
         }
 
         // Render shadows.
-        render_background_geometry(&mut self.world);
+        if self.debug_draw.shadows {
+            self.shadow_map.draw(&mut self.world);
+            render_dynamic_shadows(&mut self.world);
+        }
 
         // Render main object sprites.
         render_foreground_geometry(&mut self.world);
+
+        // Render debug visualization on top of everything else.
+        if self.debug_draw.walkability {
+            render_walkability(&self.walkability);
+        }
+
+        if self.debug_draw.sprite_bounds {
+            render_sprite_bounds(&mut self.world);
+        }
+
+        if self.debug_draw.front_points {
+            render_front_points(&mut self.world);
+        }
+
+        if self.debug_draw.colliders {
+            render_physics_colliders(&self.physics);
+        }
+
+        set_default_camera();
     }
 }
 
@@ -156,8 +1039,11 @@ pub fn update_sprite_draw_call(
     offset: Vec2,
     front_point: Vec2,
 ) {
-    let tex = draw_call.texture.as_ref().unwrap();
-    let extent = get_texture_size(&tex) * scale;
+    // Sprites without a texture (e.g. in headless scenes) still get sorted, but have no extent.
+    let extent = match draw_call.texture {
+        Some(_) => draw_call.source_size().unwrap_or(Vec2::ZERO) * scale,
+        None => Vec2::ZERO,
+    };
     let offset = offset * scale;
     let origin = world_to_screen(root_position) - extent * 0.5 - offset;
     let front_point = root_position + front_point * scale;
@@ -165,6 +1051,7 @@ pub fn update_sprite_draw_call(
     draw_call.origin = origin;
     draw_call.extent = extent;
     draw_call.order = front_point.x + front_point.y;
+    draw_call.front_point = front_point;
 }
 
 pub fn init_static_scene_objects(world: &mut World) {
@@ -193,6 +1080,36 @@ pub fn init_static_scene_objects(world: &mut World) {
     }
 }
 
+// Precomputes draw calls of a single static object, e.g. after it's been spawned or moved.
+pub fn update_static_scene_object(world: &mut World, object: Entity) -> anyhow::Result<()> {
+    let parts = world.get::<SceneObjectComponent>(object)?.parts.clone();
+
+    for part in parts {
+        if let Ok((root_transform, asset, dc)) = world.query_one_mut::<(
+            &RootTransformComponent,
+            &AssetTransformComponent,
+            &mut SpriteDrawCallComponent,
+        )>(part)
+        {
+            update_sprite_draw_call(
+                &mut dc.draw_call,
+                root_transform.position,
+                root_transform.scale,
+                asset.transform.position.vec2(),
+                asset.transform.front_point.vec2(),
+            );
+        }
+
+        if let Ok((root_transform, shadow)) =
+            world.query_one_mut::<(&RootTransformComponent, &mut SceneObjectShadowComponent)>(part)
+        {
+            shadow.update_draw_calls(root_transform.position, root_transform.scale);
+        }
+    }
+
+    Ok(())
+}
+
 pub fn spawn_static_scene_object(
     world: &mut World,
     physics: &mut PhysicsWorld,
@@ -200,78 +1117,215 @@ pub fn spawn_static_scene_object(
     position: Vec2,
     scale: f32,
     orientation: AssetOrientation,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Entity> {
     let orientation_data = asset.metadata.get_orientation(orientation)?;
 
-    // The necessary disclaimer here: this is my actual first time working with an ECS, so I'm not
-    // aware of the best practices when it comes to splitting entities into components.
+    let object = spawn_scene_object_root(world, &asset.path, position, scale, orientation);
+    let mut parts = vec![];
 
-    // The task is not clear on how these entities will be used, so I'm assuming a spawn-and-forget
-    // type of static scene objects. If we needed to dynamically manage the objects (e.g. constantly
-    // spawn and remove them), I'd probably organize components differently so that there's a single
-    // entity containing all of the sprites and primitive subcomponents.
+    // Each image of the object is spawned as a separate entity, linked to the object's root entity.
+    // The root entity keeps track of all of its parts, so that the object can be managed as a whole.
 
     for (name, data) in &orientation_data.images {
         let mut builder = EntityBuilder::new();
 
+        builder.add(SceneObjectPartComponent { object });
         builder.add(RootTransformComponent { position, scale });
 
         builder.add(AssetTransformComponent {
             transform: data.transform,
         });
 
+        // Animated images start at their first frame, so that the draw call gets the frame size
+        // right away.
+        let source = data.animation.as_ref().map(|clip| clip.frames[0].rect());
+
         builder.add(SpriteDrawCallComponent {
             draw_call: SpriteDrawCall {
-                texture: Some(asset.get_texture_resource(name)?),
+                texture: asset.get_texture_resource(name)?,
+                source,
                 ..Default::default()
             },
         });
 
-        if let Some(primitives) = data.get_primitives(AssetPrimitiveType::Shadow) {
+        if let Some(clip) = &data.animation {
+            let clips = HashMap::from([(IDLE_CLIP.to_owned(), clip.clone())]);
+            builder.add(AnimationComponent::new(clips, IDLE_CLIP));
+        }
+
+        let shadows = data.shadows().cloned().collect::<Vec<_>>();
+        if !shadows.is_empty() {
             builder.add(SceneObjectShadowComponent {
-                primitives,
+                primitives: shadows,
+                opacity: asset.metadata.shadow_opacity,
                 draw_calls: vec![],
             });
         }
 
-        if let Some(primitives) = data.get_primitives(AssetPrimitiveType::Collider) {
-            let bodies = primitives
+        let colliders = data.colliders().collect::<Vec<_>>();
+        if !colliders.is_empty() {
+            let bodies = colliders
                 .iter()
-                .map(|primitive| {
-                    let primitive_offset = primitive.shape.position.vec2() * scale;
-                    let primitive_origin = position + primitive_offset;
-                    let primitive_half_height = primitive.height.unwrap() * scale * 0.5;
-                    let primitive_half_extent_2d = primitive.shape.scale.vec2() * scale * 0.5;
-
-                    // Body position should be at the center of the cuboid collider, so add half-extent
-                    // to the primitive origin.
-                    let body_origin = vec3(
-                        primitive_origin.x + primitive_half_extent_2d.x,
-                        primitive_origin.y + primitive_half_extent_2d.y,
-                        primitive_half_height,
-                    );
+                .map(|primitive| create_static_collider_body(physics, primitive, position, scale))
+                .collect::<anyhow::Result<Vec<_>>>()?;
 
-                    let collider_half_extent = vec3(
-                        primitive_half_extent_2d.x,
-                        primitive_half_extent_2d.y,
-                        primitive_half_height,
-                    );
+            builder.add(PhysicsBodyCollectionComponent { handles: bodies });
+        }
 
-                    // Again, the assumption here is that scene objects are immovable and have fully
-                    // static physics bodies.
-                    physics.create_body_cuboid(RigidBodyType::Static, body_origin, collider_half_extent)
-                })
-                .collect();
+        parts.push(world.spawn(builder.build()));
+    }
 
-            builder.add(PhysicsBodyCollectionComponent { handles: bodies });
+    world.get_mut::<SceneObjectComponent>(object)?.parts = parts;
+
+    Ok(object)
+}
+
+// Ground footprints of the colliders of a static object, in world space.
+fn static_object_footprints(
+    asset: &Asset,
+    position: Vec2,
+    scale: f32,
+    orientation: AssetOrientation,
+) -> anyhow::Result<Vec<Vec<Vec2>>> {
+    let orientation_data = asset.metadata.get_orientation(orientation)?;
+
+    Ok(orientation_data
+        .images
+        .values()
+        .flat_map(|image| image.colliders())
+        .map(|collider| {
+            collider
+                .shape
+                .outline()
+                .into_iter()
+                .map(|x| position + x * scale)
+                .collect()
+        })
+        .collect())
+}
+
+fn create_static_collider_body(
+    physics: &mut PhysicsWorld,
+    primitive: &AssetColliderPrimitive,
+    position: Vec2,
+    scale: f32,
+) -> anyhow::Result<RigidBodyHandle> {
+    let shape = &primitive.shape;
+    let primitive_origin = position + shape.position.vec2() * scale;
+    let primitive_half_height = primitive.height * scale * 0.5;
+    let primitive_half_extent_2d = shape.scale.vec2() * scale * 0.5;
+
+    // Body position should be at the center of the collider bounds, so add half-extent to the
+    // primitive origin.
+    let body_origin = (primitive_origin + primitive_half_extent_2d).extend(primitive_half_height);
+
+    // Again, the assumption here is that scene objects are immovable and have fully static physics
+    // bodies.
+    let body = match shape.shape {
+        AssetShapeType::Rect => physics.create_body_cuboid(
+            RigidBodyType::Static,
+            body_origin,
+            primitive_half_extent_2d.extend(primitive_half_height),
+        ),
+        AssetShapeType::Circle => physics.create_body_cylinder(
+            RigidBodyType::Static,
+            body_origin,
+            primitive_half_extent_2d.x,
+            primitive_half_height,
+        ),
+        AssetShapeType::Ellipse | AssetShapeType::Polygon => {
+            // Extrude the outline into a prism, relative to the body origin.
+            let outline = shape
+                .outline()
+                .into_iter()
+                .map(|x| position + x * scale - body_origin.truncate())
+                .collect::<Vec<_>>();
+            let points = outline
+                .iter()
+                .flat_map(|x| [x.extend(-primitive_half_height), x.extend(primitive_half_height)])
+                .collect::<Vec<_>>();
+
+            match physics.create_body_convex_hull(RigidBodyType::Static, body_origin, &points) {
+                Some(body) => body,
+                None => anyhow::bail!("degenerate {:?} collider shape", shape.shape),
+            }
         }
+    };
+
+    Ok(body)
+}
+
+pub fn move_static_scene_object(
+    world: &mut World,
+    physics: &mut PhysicsWorld,
+    object: Entity,
+    position: Vec2,
+) -> anyhow::Result<()> {
+    let offset = {
+        let mut root_transform = world.get_mut::<RootTransformComponent>(object)?;
+        let offset = position - root_transform.position;
+        root_transform.position = position;
+        offset
+    };
+
+    let parts = world.get::<SceneObjectComponent>(object)?.parts.clone();
+
+    for part in parts {
+        let (root_transform, bodies) =
+            world.query_one_mut::<(&mut RootTransformComponent, Option<&PhysicsBodyCollectionComponent>)>(part)?;
 
-        world.spawn(builder.build());
+        root_transform.position = position;
+
+        // Bodies are placed relative to the object position, so simply shift them along.
+        for handle in bodies.map_or(&[][..], |bodies| bodies.handles.as_slice()) {
+            let translation = physics.get_body_translation(*handle);
+            physics.set_body_translation(*handle, translation + offset.extend(0.0));
+        }
     }
 
+    update_static_scene_object(world, object)
+}
+
+pub fn despawn_static_scene_object(
+    world: &mut World,
+    physics: &mut PhysicsWorld,
+    object: Entity,
+) -> anyhow::Result<()> {
+    let parts = world.get::<SceneObjectComponent>(object)?.parts.clone();
+
+    for part in parts {
+        if let Ok(bodies) = world.get::<PhysicsBodyCollectionComponent>(part) {
+            for handle in &bodies.handles {
+                physics.remove_body(*handle);
+            }
+        }
+
+        world.despawn(part)?;
+    }
+
+    world.despawn(object)?;
+
     Ok(())
 }
 
+// Root entity of a static object, remembering which asset and orientation it was spawned from.
+fn spawn_scene_object_root(
+    world: &mut World,
+    asset_path: &str,
+    position: Vec2,
+    scale: f32,
+    orientation: AssetOrientation,
+) -> Entity {
+    world.spawn((
+        RootTransformComponent { position, scale },
+        SceneObjectComponent {
+            asset_path: asset_path.to_owned(),
+            orientation,
+            parts: vec![],
+        },
+    ))
+}
+
 pub fn spawn_character(
     world: &mut World,
     physics: &mut PhysicsWorld,
@@ -286,22 +1340,37 @@ pub fn spawn_character(
     });
     builder.add(CharacterVelocityComponent {
         velocity: vec2(0.0, 0.0),
+        movement_speed: params.movement_speed,
     });
-    builder.add(CharacterTransformComponent {
-        offset: params.offset,
-        front_point: params.front_point,
-    });
+    let sprites = CharacterSpritesComponent::new(params.sprites, params.facing);
+    let clips = sprites.current().map(|sprite| sprite.clips.clone()).unwrap_or_default();
+    builder.add(AnimationComponent::new(clips, IDLE_CLIP));
+    builder.add(sprites);
     builder.add(SpriteDrawCallComponent {
-        draw_call: SpriteDrawCall {
-            texture: Some(params.texture),
-            ..Default::default()
-        },
+        draw_call: SpriteDrawCall::default(),
     });
 
     // Origin should be the center of the cuboid.
     let physics_body_origin = params.physics_body_origin * scale + vec3(params.position.x, params.position.y, 0.0);
     let physics_collider_half_extent = params.physics_collider_half_extent * scale;
 
+    builder.add(CharacterPathComponent {
+        half_extent: physics_collider_half_extent,
+        waypoints: VecDeque::new(),
+    });
+
+    // Shadow footprint matches the collider, so that the character stands on the floor instead of
+    // floating above it.
+    let shadow_radii = physics_collider_half_extent.truncate();
+    builder.add(DynamicShadowComponent {
+        draw_call: DynamicShadowDrawCall {
+            center: physics_body_origin.truncate(),
+            radii: shadow_radii,
+            softness: shadow_radii.min_element(),
+            opacity: DEFAULT_SHADOW_OPACITY,
+        },
+    });
+
     builder.add(PhysicsBodyComponent {
         // Ideally, character physics body should be a capsule to be able to walk up the stairs etc.
         handle: physics.create_body_cuboid(
@@ -313,3 +1382,513 @@ pub fn spawn_character(
 
     world.spawn(builder.build())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_objects(description: &SceneDescription) -> Vec<&SceneObjectDescription> {
+        let mut objects = description.objects.iter().collect::<Vec<_>>();
+        let key = |object: &SceneObjectDescription| (object.asset.clone(), object.position.x, object.position.y);
+        objects.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        objects
+    }
+
+    #[test]
+    fn scene_file_round_trip() {
+        let source = default_asset_source();
+        let original = load_scene_description(&source, "assets/demo_scene.json").unwrap();
+        let mut scene = Scene::load_headless_from_file("assets/demo_scene.json").unwrap();
+
+        // Unique per process, so that concurrent test runs don't overwrite each other's file.
+        let path = std::env::temp_dir().join(format!("sophya_scene_round_trip_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        scene.save(path).unwrap();
+        let saved = load_scene_description(&source, &format!("file://{path}")).unwrap();
+
+        // Make sure the saved file loads as well.
+        Scene::load_from_source(&source, &format!("file://{path}"), true).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let mut original_assets = original.assets.clone();
+        original_assets.sort();
+
+        assert_eq!(saved.scale, original.scale);
+        assert_eq!(saved.background, original.background);
+        assert_eq!(saved.assets, original_assets);
+        assert_eq!(sorted_objects(&saved), sorted_objects(&original));
+        assert_eq!(saved.player, original.player);
+        assert_eq!(saved.npcs, original.npcs);
+        assert_eq!(saved.spawn_points, original.spawn_points);
+        assert_eq!(saved.doorways, original.doorways);
+    }
+
+    #[test]
+    fn headless_player_moves() {
+        let mut scene = Scene::load_headless_from_file("assets/demo_scene.json").unwrap();
+        let start = scene.player_position().unwrap();
+
+        // Hold `D` for a second.
+        for _ in 0..60 {
+            scene.set_player_movement_input(vec2(1.0, 0.0));
+            scene.simulate(1.0 / 60.0);
+        }
+
+        let end = scene.player_position().unwrap();
+
+        assert!(end.distance(start) > 50.0);
+
+        // The shadow follows the player.
+        let player_character = scene.player_character.unwrap();
+        let shadow = scene.world.get::<DynamicShadowComponent>(player_character).unwrap();
+        assert_eq!(shadow.draw_call.center, end);
+
+        // The walk cycle plays at the pace of the movement.
+        let animation = scene.world.get::<AnimationComponent>(player_character).unwrap();
+        assert_eq!(animation.current, WALK_CLIP);
+        assert!((animation.speed - 1.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn player_walks_equally_fast_in_all_directions() {
+        let mut scene = Scene::load_headless_from_file("assets/demo_scene.json").unwrap();
+        let player_character = scene.player_character.unwrap();
+
+        for input in [vec2(1.0, 0.0), vec2(0.0, 1.0), vec2(-1.0, -1.0), vec2(1.0, -1.0)] {
+            scene.set_player_movement_input(input.normalize());
+
+            let vel_comp = scene.world.get::<CharacterVelocityComponent>(player_character).unwrap();
+            assert!(
+                (vel_comp.velocity.length() - vel_comp.movement_speed).abs() < 0.01,
+                "input {input} moves at {}",
+                vel_comp.velocity.length()
+            );
+        }
+    }
+
+    #[test]
+    fn objects_move_and_despawn() {
+        let source = DirectorySource::new("");
+        let assets = load_asset_bundle_headless(&source, &["assets/table.json"]).unwrap();
+
+        let mut scene = Scene::new(0.25, None);
+        let object = scene
+            .spawn_static_object(&assets[0], vec2(0.0, 0.0), AssetOrientation::East)
+            .unwrap();
+        scene.initialize();
+
+        // Front points of the object's draw calls and positions of its physics bodies.
+        let parts = scene.world.get::<SceneObjectComponent>(object).unwrap().parts.clone();
+        let snapshot = |scene: &Scene| {
+            let mut front_points = vec![];
+            let mut bodies = vec![];
+
+            for part in &parts {
+                let dc = scene.world.get::<SpriteDrawCallComponent>(*part).unwrap();
+                front_points.push(dc.draw_call.front_point);
+
+                if let Ok(collection) = scene.world.get::<PhysicsBodyCollectionComponent>(*part) {
+                    bodies.extend(
+                        collection
+                            .handles
+                            .iter()
+                            .map(|handle| scene.physics.get_body_translation_2d(*handle)),
+                    );
+                }
+            }
+
+            (front_points, bodies)
+        };
+
+        let (front_points, bodies) = snapshot(&scene);
+        assert!(!bodies.is_empty());
+        assert!(bodies.iter().all(|x| !scene.walkability.is_walkable(*x)));
+
+        let offset = vec2(300.0, 100.0);
+        scene.move_object(object, offset).unwrap();
+
+        // Draw calls, bodies and the walkability obstacle all move along.
+        let (moved_front_points, moved_bodies) = snapshot(&scene);
+        let moved_by_offset = |from: &[Vec2], to: &[Vec2]| {
+            from.len() == to.len() && from.iter().zip(to).all(|(a, b)| (*a + offset).distance(*b) < 0.001)
+        };
+
+        assert!(moved_by_offset(&front_points, &moved_front_points));
+        assert!(moved_by_offset(&bodies, &moved_bodies));
+        assert!(bodies.iter().all(|x| scene.walkability.is_walkable(*x)));
+        assert!(moved_bodies.iter().all(|x| !scene.walkability.is_walkable(*x)));
+
+        // Despawning removes everything.
+        scene.despawn_object(object).unwrap();
+
+        assert!(!scene.world.contains(object));
+        assert!(parts.iter().all(|part| !scene.world.contains(*part)));
+        assert_eq!(scene.physics.collider_boxes().count(), 0);
+        assert!(moved_bodies.iter().all(|x| scene.walkability.is_walkable(*x)));
+    }
+
+    // Scene with a table at the origin, along with the bounds of the table's colliders.
+    // Size of the physics body of characters spawned by tests, before scaling.
+    const CHARACTER_HALF_EXTENT: [f32; 3] = [40.0, 40.0, 160.0];
+
+    fn character_params(position: Vec2) -> CharacterSpawnParams {
+        let half_extent = Vec3::from(CHARACTER_HALF_EXTENT);
+
+        CharacterSpawnParams {
+            sprites: Default::default(),
+            facing: AssetOrientation::South,
+            position,
+            movement_speed: 200.0,
+            physics_body_origin: half_extent,
+            physics_collider_half_extent: half_extent,
+        }
+    }
+
+    // Spawn position that puts the physics body of a character spawned with `character_params` at
+    // the given position.
+    fn character_position(body_position: Vec2, scale: f32) -> Vec2 {
+        body_position - Vec3::from(CHARACTER_HALF_EXTENT).truncate() * scale
+    }
+
+    // Scene with a table in the way of walking characters. Returns the center of the table's
+    // colliders and half of their size.
+    fn scene_with_table() -> (Scene, Vec2, Vec2) {
+        let source = DirectorySource::new("");
+        let assets = load_asset_bundle_headless(&source, &["assets/table.json"]).unwrap();
+        let table = &assets[0];
+
+        let mut scene = Scene::new(0.25, None);
+        let table_position = vec2(0.0, 0.0);
+        scene
+            .spawn_static_object(table, table_position, AssetOrientation::East)
+            .unwrap();
+
+        let mut min = vec2(f32::MAX, f32::MAX);
+        let mut max = vec2(f32::MIN, f32::MIN);
+        for image in table
+            .metadata
+            .get_orientation(AssetOrientation::East)
+            .unwrap()
+            .images
+            .values()
+        {
+            for collider in image.colliders() {
+                let collider_min = table_position + collider.shape.position.vec2() * scene.scale;
+                min = min.min(collider_min);
+                max = max.max(collider_min + collider.shape.scale.vec2() * scene.scale);
+            }
+        }
+
+        (scene, (min + max) * 0.5, (max - min) * 0.5)
+    }
+
+    #[test]
+    fn player_walks_around_objects() {
+        let (mut scene, center, half_size) = scene_with_table();
+
+        // Start and target on opposite sides of the table, with the table right in the way.
+        let distance = half_size.x + 30.0;
+        let start = center - vec2(distance, 0.0);
+        let target = center + vec2(distance, 0.0);
+
+        scene.spawn_player(character_params(character_position(start, scene.scale)));
+        scene.initialize();
+
+        assert!(scene.move_player_to(target));
+
+        // No input doesn't interrupt walking.
+        for _ in 0..600 {
+            scene.set_player_movement_input(Vec2::ZERO);
+            scene.simulate(1.0 / 60.0);
+        }
+
+        assert!(scene.player_position().unwrap().distance(target) < WAYPOINT_RADIUS * 2.0);
+
+        // Targets inside of objects can't be reached.
+        assert!(!scene.move_player_to(center));
+    }
+
+    // Simulates the scene for the given number of frames, and returns the indices of the waypoints
+    // the patrolling NPC passes by, in order.
+    fn patrol_order(scene: &mut Scene, npc: Entity, frames: usize) -> Vec<usize> {
+        let waypoints = match &scene.world.get::<AiComponent>(npc).unwrap().behaviour {
+            AiBehaviour::Patrol { waypoints } => waypoints.clone(),
+            behaviour => panic!("NPC doesn't patrol, but has {behaviour:?}"),
+        };
+
+        let mut visited = vec![];
+
+        for _ in 0..frames {
+            scene.simulate(1.0 / 60.0);
+
+            let position = scene.character_body_position(npc).unwrap();
+            let waypoint = waypoints.iter().position(|x| x.distance(position) < 2.0);
+
+            if let Some(waypoint) = waypoint {
+                if visited.last() != Some(&waypoint) {
+                    visited.push(waypoint);
+                }
+            }
+        }
+
+        visited
+    }
+
+    #[test]
+    fn npc_patrols_waypoints() {
+        let mut scene = Scene::new(0.25, None);
+        let waypoints = vec![vec2(60.0, 10.0), vec2(60.0, 60.0)];
+        let npc = scene
+            .spawn_npc(character_params(vec2(0.0, 0.0)), AiBehaviour::Patrol { waypoints })
+            .unwrap();
+        scene.initialize();
+
+        // Waypoints are visited in order, over and over again.
+        let visited = patrol_order(&mut scene, npc, 180);
+        assert!(visited.starts_with(&[0, 1, 0, 1]), "visited {visited:?}");
+    }
+
+    #[test]
+    fn npc_patrols_around_objects() {
+        let (mut scene, center, half_size) = scene_with_table();
+
+        // Waypoints on opposite sides of the table, with the table right in the way.
+        let distance = half_size.x + 30.0;
+        let waypoints = vec![center - vec2(distance, 0.0), center + vec2(distance, 0.0)];
+
+        // Paths keep characters of the player's size clear of obstacles, so there needs to be one.
+        scene.spawn_player(character_params(vec2(-300.0, -300.0)));
+
+        let npc = scene
+            .spawn_npc(
+                character_params(character_position(waypoints[0], scene.scale)),
+                AiBehaviour::Patrol { waypoints },
+            )
+            .unwrap();
+        scene.initialize();
+
+        let visited = patrol_order(&mut scene, npc, 600);
+        assert!(visited.starts_with(&[0, 1, 0]), "visited {visited:?}");
+    }
+
+    #[test]
+    fn npc_patrols_past_covered_waypoints() {
+        let (mut scene, center, half_size) = scene_with_table();
+        scene.spawn_player(character_params(vec2(-300.0, -300.0)));
+
+        // The middle waypoint is inside the table, so the closest the NPC gets is next to it.
+        let distance = half_size.y + 30.0;
+        let waypoints = vec![center - vec2(0.0, distance), center, center + vec2(0.0, distance)];
+
+        let npc = scene
+            .spawn_npc(
+                character_params(character_position(waypoints[0], scene.scale)),
+                AiBehaviour::Patrol { waypoints },
+            )
+            .unwrap();
+        scene.initialize();
+
+        let visited = patrol_order(&mut scene, npc, 600);
+        assert!(visited.contains(&2), "visited {visited:?}");
+    }
+
+    #[test]
+    fn npc_follows_player() {
+        let mut scene = Scene::new(0.25, None);
+        scene.spawn_player(character_params(vec2(0.0, 0.0)));
+
+        let player_character = scene.player_character.unwrap();
+        let follower = scene
+            .spawn_npc(
+                character_params(vec2(200.0, 0.0)),
+                AiBehaviour::Follow {
+                    target: player_character,
+                    distance: 50.0,
+                },
+            )
+            .unwrap();
+        scene.initialize();
+
+        for _ in 0..120 {
+            scene.simulate(1.0 / 60.0);
+        }
+
+        let player = scene.player_position().unwrap();
+        let follower = scene.character_body_position(follower).unwrap();
+        assert!((follower.distance(player) - 50.0).abs() < 2.0);
+
+        // Tries to walk right into the player, but bumps into it instead.
+        let pusher = scene
+            .spawn_npc(
+                character_params(vec2(-200.0, 0.0)),
+                AiBehaviour::Follow {
+                    target: player_character,
+                    distance: 0.0,
+                },
+            )
+            .unwrap();
+
+        for _ in 0..120 {
+            scene.simulate(1.0 / 60.0);
+
+            let player = scene.player_position().unwrap();
+            let pusher = scene.character_body_position(pusher).unwrap();
+
+            // Colliders are 20 units wide, so bodies can't get any closer than that.
+            assert!(pusher.distance(player) > 19.5);
+        }
+    }
+
+    #[test]
+    fn player_stays_within_bounds() {
+        let mut scene = Scene::new(0.25, None);
+        // A diamond, so that walls don't line up with the collider of the player.
+        scene
+            .set_bounds(vec![
+                vec2(0.0, -100.0),
+                vec2(100.0, 0.0),
+                vec2(0.0, 100.0),
+                vec2(-100.0, 0.0),
+            ])
+            .unwrap();
+        scene.spawn_player(character_params(vec2(-10.0, -10.0)));
+        scene.initialize();
+
+        // Hold `D` for a few seconds, which is way longer than it takes to reach the wall.
+        for _ in 0..180 {
+            scene.set_player_movement_input(vec2(1.0, 0.0));
+            scene.simulate(1.0 / 60.0);
+        }
+
+        // The player's collider corner closest to the wall touches it.
+        let position = scene.player_position().unwrap();
+        let corner = position + vec2(10.0, -10.0);
+        assert!(corner.x - corner.y <= 100.5);
+        assert!(corner.x - corner.y > 99.0);
+
+        // Paths don't lead out of the bounds either.
+        assert!(!scene.move_player_to(vec2(200.0, 0.0)));
+    }
+
+    #[test]
+    fn player_walks_past_concave_corners() {
+        let mut scene = Scene::new(0.25, None);
+        // L-shaped room, without the top right quarter. Walls at the inner corner must not stick
+        // into the room.
+        scene
+            .set_bounds(vec![
+                vec2(0.0, 0.0),
+                vec2(100.0, 0.0),
+                vec2(100.0, 100.0),
+                vec2(200.0, 100.0),
+                vec2(200.0, 200.0),
+                vec2(0.0, 200.0),
+            ])
+            .unwrap();
+        scene.spawn_player(character_params(vec2(20.0, 120.0)));
+        scene.initialize();
+
+        // Right along the inner corner, just below it.
+        let target = vec2(170.0, 130.0);
+        assert!(scene.move_player_to(target));
+
+        for _ in 0..180 {
+            scene.simulate(1.0 / 60.0);
+        }
+
+        assert!(scene.player_position().unwrap().distance(target) < WAYPOINT_RADIUS * 2.0);
+
+        // The walls next to the inner corner still hold.
+        let handle = scene
+            .world
+            .get::<PhysicsBodyComponent>(scene.player_character.unwrap())
+            .unwrap()
+            .handle;
+
+        for _ in 0..60 {
+            scene.physics.set_body_linear_velocity_2d(handle, vec2(0.0, -200.0));
+            scene.simulate(1.0 / 60.0);
+        }
+
+        let position = scene.player_position().unwrap();
+        assert!((position.y - 110.0).abs() < 1.0, "player got to {position}");
+    }
+
+    fn doorway(position: Vec2) -> DoorwayDescription {
+        DoorwayDescription {
+            position: position.into(),
+            size: Point { x: 40.0, y: 40.0 },
+            scene: "assets/demo_hallway.json".to_owned(),
+            spawn_point: "fromRoom".to_owned(),
+        }
+    }
+
+    #[test]
+    fn player_walks_through_doorway() {
+        let mut scene = Scene::new(0.25, None);
+        scene.spawn_doorway(doorway(vec2(60.0, -20.0)));
+        scene.spawn_player(character_params(vec2(-10.0, -10.0)));
+        scene.initialize();
+
+        assert!(scene.move_player_to(vec2(80.0, 0.0)));
+
+        let mut transitions = vec![];
+        let mut entered_at = None;
+
+        for _ in 0..120 {
+            scene.simulate(1.0 / 60.0);
+
+            if let Some(transition) = scene.take_transition() {
+                transitions.push(transition);
+                entered_at = scene.player_position();
+            }
+        }
+
+        // Walking into the doorway stops the player there, even though it was sent further.
+        let entered_at = entered_at.unwrap();
+        let position = scene.player_position().unwrap();
+        assert!(position.distance(entered_at) < 1.0, "player walked on to {position}");
+
+        // Standing in the doorway doesn't send the player through it again.
+        assert_eq!(
+            transitions,
+            [SceneTransition {
+                scene: "assets/demo_hallway.json".to_owned(),
+                spawn_point: "fromRoom".to_owned(),
+            }]
+        );
+
+        // Neither does coming out in one.
+        let mut scene = Scene::new(0.25, None);
+        scene.spawn_doorway(doorway(vec2(-20.0, -20.0)));
+        scene.spawn_player(character_params(vec2(-10.0, -10.0)));
+        scene.initialize();
+
+        for _ in 0..10 {
+            scene.simulate(1.0 / 60.0);
+            assert_eq!(scene.take_transition(), None);
+        }
+    }
+
+    #[test]
+    fn doorways_lead_to_spawn_points() {
+        let source = default_asset_source();
+
+        for path in ["assets/demo_scene.json", "assets/demo_hallway.json"] {
+            let description = load_scene_description(&source, path).unwrap();
+
+            for doorway in &description.doorways {
+                let target = load_scene_description(&source, &doorway.scene).unwrap();
+                assert!(
+                    target.spawn_points.contains_key(&doorway.spawn_point),
+                    "{path}: no spawn point {} in {}",
+                    doorway.spawn_point,
+                    doorway.scene
+                );
+
+                Scene::load_headless_from_file(&doorway.scene).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use crate::asset::AssetOrientation;
+
+// Request to switch to another scene, raised when the player walks through a doorway. See
+// `Scene::take_transition`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SceneTransition {
+    // Path of the scene to load.
+    pub scene: String,
+    // Name of the spawn point in that scene the player comes out at.
+    pub spawn_point: String,
+}
+
+// What the player keeps when switching scenes, see `SceneLoader::start_transition`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PlayerState {
+    // Path of the character definition.
+    pub character: String,
+    pub facing: AssetOrientation,
+}
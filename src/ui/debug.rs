@@ -0,0 +1,44 @@
+use crate::{render::*, scene::Scene};
+
+// Debug menu allowing to toggle scene debug visualization. The menu itself is hidden by default and
+// toggled by a hotkey (see `DemoScene`).
+#[derive(Default)]
+pub struct DebugMenu {
+    pub visible: bool,
+}
+
+impl DebugMenu {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn ui(&mut self, egui_ctx: &egui::CtxRef, scene: &mut Scene) {
+        if !self.visible {
+            return;
+        }
+
+        egui::Window::new("Debug")
+            .open(&mut self.visible)
+            .resizable(false)
+            .show(egui_ctx, |ui| {
+                let settings = scene.debug_draw_settings_mut();
+                ui.checkbox(&mut settings.colliders, "3D colliders");
+                ui.checkbox(&mut settings.shadows, "Shadows");
+                ui.checkbox(&mut settings.sprite_bounds, "Sprite bounds");
+                ui.checkbox(&mut settings.front_points, "Front points");
+                ui.checkbox(&mut settings.walkability, "Walkability");
+
+                ui.separator();
+
+                let camera = scene.camera_mut();
+                ui.label("View scale");
+                ui.radio_value(&mut camera.scale_policy, ViewScalePolicy::FitMap, "Fit map");
+                ui.radio_value(&mut camera.scale_policy, ViewScalePolicy::PixelPerfect, "Pixel perfect");
+                ui.radio_value(
+                    &mut camera.scale_policy,
+                    ViewScalePolicy::FixedWorldUnits,
+                    "Fixed world units",
+                );
+            });
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{asset::*, math::*, scene::*};
+use hecs::Entity;
+use macroquad::prelude::*;
+
+const SELECTION_COLOR: Color = Color::new(0.2, 0.6, 1.0, 1.0);
+
+// In-game level editor. Allows placing objects from the scene's asset bundle, selecting, dragging,
+// rotating and deleting them, and saving the result back to a scene file.
+//
+// Controls (while enabled): left click on an empty spot places the asset picked in the palette,
+// left click on an object selects it and dragging moves it, `R` rotates the selected object (or
+// the asset to be placed), `Delete` removes the selected object. Keys are ignored while typing in
+// the editor window.
+pub struct LevelEditor {
+    pub enabled: bool,
+    save_path: String,
+    // Asset path of the asset to place, or `None` if clicking should only select objects.
+    palette_asset: Option<String>,
+    palette_orientation: AssetOrientation,
+    selection: Option<Entity>,
+    // Offset from the cursor to the dragged object, in world space.
+    drag_offset: Option<Vec2>,
+    // Last error, shown in the editor window until dismissed.
+    error: Option<String>,
+}
+
+impl LevelEditor {
+    pub fn new(save_path: &str) -> Self {
+        Self {
+            enabled: false,
+            save_path: save_path.to_owned(),
+            palette_asset: None,
+            palette_orientation: AssetOrientation::North,
+            selection: None,
+            drag_offset: None,
+            error: None,
+        }
+    }
+
+    // Shows an error of `ui` or `update` in the editor window.
+    pub fn report_error(&mut self, err: anyhow::Error) {
+        println!("level editor error: {err}");
+        self.error = Some(err.to_string());
+    }
+
+    // Switches to editing another scene, e.g. after walking through a doorway. Forgets the
+    // selection, since it belongs to the previous scene.
+    pub fn set_scene(&mut self, save_path: &str) {
+        self.save_path = save_path.to_owned();
+        self.selection = None;
+        self.drag_offset = None;
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.selection = None;
+        self.drag_offset = None;
+        self.error = None;
+    }
+
+    pub fn ui(&mut self, egui_ctx: &egui::CtxRef, scene: &mut Scene) -> anyhow::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let mut delete_selection = false;
+        let mut save = false;
+
+        egui::Window::new("Editor").resizable(false).show(egui_ctx, |ui| {
+            ui.label("Assets");
+
+            if ui.selectable_label(self.palette_asset.is_none(), "Select").clicked() {
+                self.palette_asset = None;
+            }
+
+            for asset in scene.assets() {
+                let selected = self.palette_asset.as_deref() == Some(asset.path.as_str());
+
+                if ui.selectable_label(selected, asset.metadata.name.as_str()).clicked() {
+                    self.palette_asset = Some(asset.path.clone());
+
+                    if !asset.metadata.orientations.contains_key(&self.palette_orientation) {
+                        self.palette_orientation = asset.metadata.next_orientation(self.palette_orientation);
+                    }
+                }
+            }
+
+            if self.palette_asset.is_some() {
+                ui.label(format!("Orientation: {:?}", self.palette_orientation));
+            }
+
+            ui.separator();
+
+            match self.selection.and_then(|object| scene.describe_object(object)) {
+                Some(object) => {
+                    ui.label(format!("Selected: {}", object.asset));
+                    ui.label(format!("Position: {:.1}, {:.1}", object.position.x, object.position.y));
+                    ui.label(format!("Orientation: {:?}", object.orientation));
+                    delete_selection = ui.button("Delete").clicked();
+                }
+                None => {
+                    ui.label("Nothing selected");
+                }
+            }
+
+            ui.separator();
+
+            ui.text_edit_singleline(&mut self.save_path);
+            save = ui.button("Save").clicked();
+
+            if let Some(error) = &self.error {
+                ui.separator();
+                ui.colored_label(egui::Color32::RED, error);
+
+                if ui.button("Dismiss").clicked() {
+                    self.error = None;
+                }
+            }
+        });
+
+        if delete_selection {
+            self.delete_selection(scene)?;
+        }
+
+        if save {
+            scene.save(&self.save_path)?;
+        }
+
+        Ok(())
+    }
+
+    // Handles mouse and keyboard input. Should be called after `ui`, so that clicks on the editor
+    // window and typing in it don't end up in the scene.
+    pub fn update(&mut self, scene: &mut Scene, pointer_captured: bool, keyboard_captured: bool) -> anyhow::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let mouse_position = scene.camera().window_to_screen(Vec2::from(mouse_position()));
+
+        if !keyboard_captured && is_key_pressed(KeyCode::R) {
+            self.rotate(scene)?;
+        }
+
+        if !keyboard_captured && is_key_pressed(KeyCode::Delete) {
+            self.delete_selection(scene)?;
+        }
+
+        let cursor_position = screen_to_world(mouse_position);
+
+        if !pointer_captured && is_mouse_button_pressed(MouseButton::Left) {
+            self.selection = scene.pick_object(mouse_position);
+            self.drag_offset = None;
+
+            if let Some(object) = self.selection.and_then(|object| scene.describe_object(object)) {
+                self.drag_offset = Some(object.position.vec2() - cursor_position);
+            } else if let Some(asset_path) = &self.palette_asset {
+                let object = scene.spawn_bundled_object(asset_path, cursor_position, self.palette_orientation)?;
+                self.selection = Some(object);
+            }
+        }
+
+        if is_mouse_button_down(MouseButton::Left) {
+            if let (Some(object), Some(drag_offset)) = (self.selection, self.drag_offset) {
+                scene.drag_object(object, cursor_position + drag_offset)?;
+            }
+        } else if self.drag_offset.take().is_some() {
+            scene.end_drag();
+        }
+
+        self.draw_selection(scene);
+
+        Ok(())
+    }
+
+    fn draw_selection(&self, scene: &Scene) {
+        if let Some(bounds) = self.selection.and_then(|object| scene.object_screen_bounds(object)) {
+            scene.camera().apply();
+            draw_rectangle_lines(bounds.x, bounds.y, bounds.w, bounds.h, 2.0, SELECTION_COLOR);
+            set_default_camera();
+        }
+    }
+
+    fn rotate(&mut self, scene: &mut Scene) -> anyhow::Result<()> {
+        if let Some(object) = self.selection.and_then(|object| scene.describe_object(object)) {
+            let orientation = match scene.assets().iter().find(|asset| asset.path == object.asset) {
+                Some(asset) => asset.metadata.next_orientation(object.orientation),
+                None => return Ok(()),
+            };
+
+            self.respawn_selection(scene, |object| object.orientation = orientation)?;
+        } else if let Some(asset) = scene
+            .assets()
+            .iter()
+            .find(|asset| Some(&asset.path) == self.palette_asset.as_ref())
+        {
+            self.palette_orientation = asset.metadata.next_orientation(self.palette_orientation);
+        }
+
+        Ok(())
+    }
+
+    fn delete_selection(&mut self, scene: &mut Scene) -> anyhow::Result<()> {
+        if let Some(object) = self.selection.take() {
+            scene.despawn_object(object)?;
+        }
+
+        self.drag_offset = None;
+
+        Ok(())
+    }
+
+    // Orientation changes the images an object consists of, so such changes are applied by
+    // despawning the selected object and spawning a modified copy of it.
+    fn respawn_selection(
+        &mut self,
+        scene: &mut Scene,
+        modify: impl FnOnce(&mut SceneObjectDescription),
+    ) -> anyhow::Result<()> {
+        if let Some(mut object) = self.selection.and_then(|object| scene.describe_object(object)) {
+            modify(&mut object);
+
+            self.delete_selection(scene)?;
+            self.selection =
+                Some(scene.spawn_bundled_object(&object.asset, object.position.vec2(), object.orientation)?);
+        }
+
+        Ok(())
+    }
+}
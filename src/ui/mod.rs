@@ -0,0 +1,5 @@
+mod debug;
+mod editor;
+
+pub use debug::*;
+pub use editor::*;
@@ -0,0 +1,238 @@
+// Collision regression tests, driven by the shipped asset metadata. Each asset is spawned exactly
+// like it is in a scene, and a character is walked at each of its colliders from every side, for
+// every orientation the asset defines. The character should stop right where it first touches a
+// collider - neither passing through, nor getting stuck before reaching it.
+
+use hecs::World;
+use macroquad::prelude::*;
+use sophya_rust_challenge::{asset::*, physics::*, scene::*};
+
+const SCALE: f32 = 0.25;
+const CHARACTER_SPEED: f32 = 200.0;
+const STEP: f32 = 1.0 / 60.0;
+const STEP_COUNT: usize = 180;
+
+// Distance the character starts away from the collider it walks at.
+const START_DISTANCE: f32 = 30.0;
+
+// Allowed difference between where the character stops and where it should, in scaled world units.
+const TOLERANCE: f32 = 0.5;
+
+fn character_half_extent() -> Vec3 {
+    vec3(40.0, 40.0, 160.0)
+}
+
+// World-space bounds of each collider of the asset in the given orientation.
+fn collider_bounds(asset: &Asset, orientation: AssetOrientation, position: Vec2) -> Vec<(Vec2, Vec2)> {
+    let orientation_data = asset.metadata.get_orientation(orientation).unwrap();
+
+    orientation_data
+        .images
+        .values()
+        .flat_map(|image| image.colliders())
+        .map(|primitive| {
+            let min = position + primitive.shape.position.vec2() * SCALE;
+            (min, min + primitive.shape.scale.vec2() * SCALE)
+        })
+        .collect()
+}
+
+// How far a box with the given half extent travels from `start` along `direction` before it touches
+// the bounds, or `None` if it never does. Starting out overlapping the bounds counts as touching
+// them right away.
+fn distance_to_contact(start: Vec2, direction: Vec2, half_extent: Vec2, (min, max): (Vec2, Vec2)) -> Option<f32> {
+    let min = min - half_extent;
+    let max = max + half_extent;
+    let mut entry = f32::MIN;
+    let mut exit = f32::MAX;
+
+    for axis in 0..2 {
+        if direction[axis] == 0.0 {
+            if start[axis] <= min[axis] || start[axis] >= max[axis] {
+                return None;
+            }
+
+            continue;
+        }
+
+        let a = (min[axis] - start[axis]) / direction[axis];
+        let b = (max[axis] - start[axis]) / direction[axis];
+        entry = entry.max(a.min(b));
+        exit = exit.min(a.max(b));
+    }
+
+    (entry < exit && exit > 0.0).then(|| entry.max(0.0))
+}
+
+// Walks a character at the collider with the given index along the given world-space direction.
+// Returns how far the character stopped from where it should have, or `None` if the character
+// can't be placed in front of the collider, e.g. since another collider of the asset is in the way.
+// Negative distance means the character went too far.
+fn walk_into_collider(asset: &Asset, orientation: AssetOrientation, collider: usize, direction: Vec2) -> Option<f32> {
+    let mut world = World::new();
+    let mut physics = PhysicsWorld::new();
+
+    let object_position = vec2(0.0, 0.0);
+    spawn_static_scene_object(&mut world, &mut physics, asset, object_position, SCALE, orientation).unwrap();
+
+    let bounds = collider_bounds(asset, orientation, object_position);
+    let (min, max) = bounds[collider];
+    let character_half_extent_2d = character_half_extent().truncate() * SCALE;
+
+    // Distance between the centers along the direction at which the character touches the collider.
+    let contact_distance = ((max - min) * 0.5 + character_half_extent_2d).dot(direction.abs());
+    let start = (min + max) * 0.5 - direction * (contact_distance + START_DISTANCE);
+
+    // The character stops at whichever collider it touches first, which isn't necessarily the one
+    // it walks at.
+    let mut expected = f32::MAX;
+
+    for collider_bounds in &bounds {
+        match distance_to_contact(start, direction, character_half_extent_2d, *collider_bounds) {
+            Some(distance) if distance <= 0.0 => return None,
+            Some(distance) => expected = expected.min(distance),
+            None => {}
+        }
+    }
+
+    // The character body is offset from its position by the body origin, see `spawn_character`.
+    let character = spawn_character(
+        &mut world,
+        &mut physics,
+        SCALE,
+        CharacterSpawnParams {
+            sprites: Default::default(),
+            facing: AssetOrientation::South,
+            position: start - character_half_extent_2d,
+            movement_speed: CHARACTER_SPEED,
+            physics_body_origin: character_half_extent(),
+            physics_collider_half_extent: character_half_extent(),
+        },
+    );
+    let handle = world.get::<PhysicsBodyComponent>(character).unwrap().handle;
+
+    for _ in 0..STEP_COUNT {
+        physics.set_body_linear_velocity_2d(handle, direction * CHARACTER_SPEED);
+        physics.update(STEP);
+    }
+
+    let travelled = (physics.get_body_translation_2d(handle) - start).dot(direction);
+
+    Some(expected - travelled)
+}
+
+fn assert_character_stops_at_colliders(asset_path: &str) {
+    let asset = load_asset_headless(&DirectorySource::new(""), asset_path).unwrap();
+    assert_character_stops_at_asset_colliders(asset_path, &asset);
+}
+
+fn assert_character_stops_at_asset_colliders(asset_path: &str, asset: &Asset) {
+    let directions = [vec2(1.0, 0.0), vec2(-1.0, 0.0), vec2(0.0, 1.0), vec2(0.0, -1.0)];
+
+    assert!(!asset.metadata.orientations.is_empty());
+
+    for orientation in asset.metadata.orientations.keys() {
+        let collider_count = collider_bounds(asset, *orientation, Vec2::ZERO).len();
+
+        for collider in 0..collider_count {
+            let mut probed = false;
+
+            for direction in directions {
+                let distance = match walk_into_collider(asset, *orientation, collider, direction) {
+                    Some(distance) => distance,
+                    None => continue,
+                };
+
+                probed = true;
+
+                assert!(
+                    distance.abs() < TOLERANCE,
+                    "{asset_path} facing {orientation:?}, collider {collider} approached along {direction}: \
+                     stopped {distance} units away from where it should"
+                );
+            }
+
+            assert!(
+                probed,
+                "{asset_path} facing {orientation:?}: collider {collider} can't be approached from any side"
+            );
+        }
+    }
+}
+
+#[test]
+fn character_stops_at_chair() {
+    assert_character_stops_at_colliders("assets/chair.json");
+}
+
+#[test]
+fn character_stops_at_table() {
+    assert_character_stops_at_colliders("assets/table.json");
+}
+
+#[test]
+fn character_stops_at_bookshelf() {
+    assert_character_stops_at_colliders("assets/bookshelf.json");
+}
+
+// Asset with a single collider of the given shape, for testing shapes the shipped assets don't use.
+fn shape_asset(shape: &str) -> Asset {
+    let metadata = r#"{
+        "name": "Shape",
+        "orientations": {
+            "N": {
+                "images": {
+                    "image": {
+                        "type": "image",
+                        "url": "shape.png",
+                        "transform": {
+                            "position": { "x": 0, "y": 0 },
+                            "scale": { "x": 400, "y": 400 },
+                            "frontPoint": { "x": 200, "y": 200 }
+                        },
+                        "primitives": {
+                            "collider": {
+                                "type": "collider",
+                                "height": 200,
+                                "shape": SHAPE
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }"#;
+
+    Asset {
+        path: String::from("shape.json"),
+        metadata: parse_asset_metadata(metadata.replace("SHAPE", shape).as_bytes()).unwrap(),
+        resources: None,
+    }
+}
+
+#[test]
+fn character_stops_at_circle() {
+    let asset =
+        shape_asset(r#"{ "position": { "x": 40, "y": 60 }, "scale": { "x": 200, "y": 200 }, "shape": "circle" }"#);
+    assert_character_stops_at_asset_colliders("circle", &asset);
+}
+
+#[test]
+fn character_stops_at_ellipse() {
+    let asset =
+        shape_asset(r#"{ "position": { "x": 40, "y": 60 }, "scale": { "x": 300, "y": 160 }, "shape": "ellipse" }"#);
+    assert_character_stops_at_asset_colliders("ellipse", &asset);
+}
+
+#[test]
+fn character_stops_at_polygon() {
+    let asset = shape_asset(
+        r#"{
+            "position": { "x": 40, "y": 60 },
+            "scale": { "x": 300, "y": 160 },
+            "shape": "polygon",
+            "points": [{ "x": 0.5, "y": 0 }, { "x": 1, "y": 0.5 }, { "x": 0.5, "y": 1 }, { "x": 0, "y": 0.5 }]
+        }"#,
+    );
+    assert_character_stops_at_asset_colliders("polygon", &asset);
+}